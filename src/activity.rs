@@ -0,0 +1,149 @@
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+use crate::detector::BUILTIN_DETECTORS;
+
+/// Manifests and lockfiles whose mtime counts as project activity
+const PROJECT_FILES: &[&str] = &[
+    "package.json",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "Cargo.toml",
+    "Cargo.lock",
+    "pyproject.toml",
+    "requirements.txt",
+    "build.gradle",
+    "Podfile",
+];
+
+/// At most this many source files are looked at per project
+const MAX_SAMPLED_FILES: usize = 500;
+
+/// How deep below the project directory source files are sampled
+const MAX_SAMPLE_DEPTH: usize = 4;
+
+/// When the project owning an artifact was last worked on: the newest mtime of
+/// its manifests and lockfiles, a bounded sample of its source files and the
+/// `.git` HEAD and index. Repositories above the scan `root` are not looked at,
+/// so a dotfiles repository in `$HOME` doesn't make every project look active.
+/// `None` when none of these could be read.
+pub fn last_activity(project_dir: &Path, root: &Path) -> Option<SystemTime> {
+    let manifests = PROJECT_FILES
+        .iter()
+        .filter_map(|name| modified(&project_dir.join(name)));
+
+    manifests
+        .chain(git_activity(project_dir, root))
+        .chain(sample_sources(project_dir))
+        .max()
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).ok()?.modified().ok()
+}
+
+/// HEAD and index mtimes of the repository containing `dir`, if its top level is
+/// at or below `root`. Commits, checkouts and staging all touch one of them.
+fn git_activity(dir: &Path, root: &Path) -> Vec<SystemTime> {
+    let Some(git) = dir
+        .ancestors()
+        .take_while(|d| d.starts_with(root))
+        .map(|d| d.join(".git"))
+        .find(|g| g.exists())
+    else {
+        return Vec::new();
+    };
+    if git.is_dir() {
+        ["HEAD", "index"]
+            .iter()
+            .filter_map(|name| modified(&git.join(name)))
+            .collect()
+    } else {
+        // A worktree or submodule, where `.git` is a file pointing elsewhere
+        modified(&git).into_iter().collect()
+    }
+}
+
+/// Modification times of up to `MAX_SAMPLED_FILES` files in the project,
+/// skipping hidden directories and build artifacts
+fn sample_sources(dir: &Path) -> Vec<SystemTime> {
+    WalkDir::new(dir)
+        .max_depth(MAX_SAMPLE_DEPTH)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_type().is_dir() || !is_skipped_dir(e.path()))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .take(MAX_SAMPLED_FILES)
+        .filter_map(|e| e.metadata().ok()?.modified().ok())
+        .collect()
+}
+
+fn is_skipped_dir(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return true;
+    };
+    name.starts_with('.') || BUILTIN_DETECTORS.iter().any(|d| d.dir_name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    fn set_mtime(path: &Path, time: SystemTime) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_newest_source_file_wins_over_artifacts() {
+        let temp = tempdir().unwrap();
+        let project = temp.path();
+        let old = SystemTime::now() - Duration::from_secs(90 * 86400);
+        let recent = SystemTime::now() - Duration::from_secs(2 * 86400);
+
+        fs::write(project.join("package.json"), "{}").unwrap();
+        set_mtime(&project.join("package.json"), old);
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(project.join("src").join("index.js"), "").unwrap();
+        set_mtime(&project.join("src").join("index.js"), recent);
+        // Files inside node_modules don't count as activity
+        fs::create_dir_all(project.join("node_modules").join("dep")).unwrap();
+        fs::write(
+            project.join("node_modules").join("dep").join("index.js"),
+            "",
+        )
+        .unwrap();
+
+        let activity = last_activity(project, project).unwrap();
+        let age = activity.elapsed().unwrap().as_secs();
+
+        assert!((2 * 86400 - 60..2 * 86400 + 60).contains(&age));
+    }
+
+    #[test]
+    fn test_git_head_counts_as_activity() {
+        let temp = tempdir().unwrap();
+        let project = temp.path().join("app");
+        fs::create_dir_all(&project).unwrap();
+        fs::create_dir_all(temp.path().join(".git")).unwrap();
+        let head = temp.path().join(".git").join("HEAD");
+        fs::write(&head, "ref: refs/heads/main\n").unwrap();
+        let committed = SystemTime::now() - Duration::from_secs(3600);
+        set_mtime(&head, committed);
+
+        assert_eq!(last_activity(&project, temp.path()), Some(committed));
+        // A repository above the scan root belongs to something else
+        assert_eq!(last_activity(&project, &project), None);
+    }
+}
@@ -0,0 +1,412 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::scanner::{DirSize, SharedLink};
+
+/// Bumped whenever the file layout changes; older caches are ignored
+const CACHE_VERSION: u32 = 4;
+
+/// How a scan uses the on-disk cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheMode {
+    /// Reuse sizes of unchanged artifacts and save the results
+    #[default]
+    Use,
+    /// Ignore what is cached, but save the fresh results
+    Refresh,
+    /// Neither read nor write the cache
+    Off,
+}
+
+/// Directory mtime as (seconds, nanoseconds) since the epoch
+type Mtime = (u64, u32);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedSize {
+    mtime: Mtime,
+    size: DirSize,
+    /// Hard-linked files that also have links outside the artifact
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    links: Vec<SharedLink>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedListing {
+    mtime: Mtime,
+    /// (name, is symlink) of each subdirectory
+    subdirs: Vec<(String, bool)>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheData {
+    version: u32,
+    /// Artifact directory sizes, keyed by path
+    sizes: HashMap<String, CachedSize>,
+    /// Subdirectories of scanned directories, keyed by path
+    listings: HashMap<String, CachedListing>,
+}
+
+/// How many artifact sizes came from the cache and how many were measured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub cached: usize,
+    pub fresh: usize,
+}
+
+impl CacheStats {
+    /// `cached 12 / fresh 3`
+    pub fn describe(&self) -> String {
+        format!("cached {} / fresh {}", self.cached, self.fresh)
+    }
+}
+
+/// Sizes and directory listings from earlier scans. A listing is reused while its
+/// directory's mtime is unchanged; a size while the artifact's `tree_mtime` is,
+/// so packages added, removed or replaced at any depth are noticed too.
+/// `--refresh` re-measures everything.
+pub struct ScanCache {
+    path: PathBuf,
+    previous: CacheData,
+    /// Whether lookups may use `previous`; with `--refresh` it is only kept so
+    /// records for other roots survive the save
+    reuse: bool,
+    current: Mutex<CacheData>,
+    cached: AtomicUsize,
+    fresh: AtomicUsize,
+}
+
+impl ScanCache {
+    /// The cache at the default location, or `None` when caching is off or
+    /// there is no cache directory
+    pub fn open(mode: CacheMode) -> Option<ScanCache> {
+        match mode {
+            CacheMode::Off => None,
+            CacheMode::Use => Some(Self::load(default_path()?)),
+            CacheMode::Refresh => Some(Self::refresh(default_path()?)),
+        }
+    }
+
+    /// Read `path`; a missing, unreadable or outdated file gives an empty cache
+    pub fn load(path: PathBuf) -> ScanCache {
+        let previous = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<CacheData>(&bytes).ok())
+            .filter(|data| data.version == CACHE_VERSION)
+            .unwrap_or_default();
+        ScanCache {
+            previous,
+            ..Self::empty(path)
+        }
+    }
+
+    /// Like `load`, but everything is measured again; records for roots that
+    /// aren't scanned are still kept on save
+    pub fn refresh(path: PathBuf) -> ScanCache {
+        ScanCache {
+            reuse: false,
+            ..Self::load(path)
+        }
+    }
+
+    /// A cache that starts out empty and is written to `path` on save
+    pub fn empty(path: PathBuf) -> ScanCache {
+        ScanCache {
+            path,
+            previous: CacheData::default(),
+            reuse: true,
+            current: Mutex::new(CacheData::default()),
+            cached: AtomicUsize::new(0),
+            fresh: AtomicUsize::new(0),
+        }
+    }
+
+    /// Size recorded for the artifact at `path` if its `tree_mtime` hasn't changed since
+    pub fn size(
+        &self,
+        path: &Path,
+        modified: Option<SystemTime>,
+    ) -> Option<(DirSize, Vec<SharedLink>)> {
+        if !self.reuse {
+            return None;
+        }
+        let key = path.to_str()?;
+        let mtime = mtime(modified)?;
+        let cached = self.previous.sizes.get(key).filter(|c| c.mtime == mtime)?;
+        self.cached.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut current) = self.current.lock() {
+            current.sizes.insert(key.to_string(), cached.clone());
+        }
+        Some((cached.size, cached.links.clone()))
+    }
+
+    /// Remember a freshly measured size
+    pub fn store_size(
+        &self,
+        path: &Path,
+        modified: Option<SystemTime>,
+        size: DirSize,
+        links: &[SharedLink],
+    ) {
+        self.fresh.fetch_add(1, Ordering::Relaxed);
+        let (Some(key), Some(mtime)) = (path.to_str(), mtime(modified)) else {
+            return;
+        };
+        if let Ok(mut current) = self.current.lock() {
+            current.sizes.insert(
+                key.to_string(),
+                CachedSize {
+                    mtime,
+                    size,
+                    links: links.to_vec(),
+                },
+            );
+        }
+    }
+
+    /// Subdirectories of `dir` as (path, is symlink), reusing the previous listing
+    /// while the directory's mtime is unchanged, or calling `list` otherwise
+    pub fn subdirs(
+        &self,
+        dir: &Path,
+        modified: Option<SystemTime>,
+        list: impl FnOnce() -> Vec<(PathBuf, bool)>,
+    ) -> Vec<(PathBuf, bool)> {
+        let (Some(key), Some(mtime)) = (dir.to_str(), mtime(modified)) else {
+            return list();
+        };
+
+        let previous = self.previous.listings.get(key).filter(|_| self.reuse);
+        let listing = match previous.filter(|l| l.mtime == mtime) {
+            Some(listing) => listing.clone(),
+            None => CachedListing {
+                mtime,
+                subdirs: list()
+                    .into_iter()
+                    .filter_map(|(path, is_link)| {
+                        Some((path.file_name()?.to_str()?.to_string(), is_link))
+                    })
+                    .collect(),
+            },
+        };
+
+        let subdirs = listing
+            .subdirs
+            .iter()
+            .map(|(name, is_link)| (dir.join(name), *is_link))
+            .collect();
+        if let Ok(mut current) = self.current.lock() {
+            current.listings.insert(key.to_string(), listing);
+        }
+        subdirs
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            cached: self.cached.load(Ordering::Relaxed),
+            fresh: self.fresh.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Write what this scan saw. Earlier records under `roots` are replaced by it,
+    /// so deleted directories drop out; records for other roots are kept.
+    pub fn save(&self, roots: &[PathBuf]) -> Result<()> {
+        let current = self
+            .current
+            .lock()
+            .map_err(|_| anyhow::anyhow!("scan cache lock poisoned"))?;
+        let outside = |key: &&String| !roots.iter().any(|root| Path::new(key).starts_with(root));
+
+        let mut data = CacheData {
+            version: CACHE_VERSION,
+            sizes: current.sizes.clone(),
+            listings: current.listings.clone(),
+        };
+        for (key, size) in self.previous.sizes.iter().filter(|(k, _)| outside(k)) {
+            data.sizes
+                .entry(key.clone())
+                .or_insert_with(|| size.clone());
+        }
+        for (key, listing) in self.previous.listings.iter().filter(|(k, _)| outside(k)) {
+            data.listings
+                .entry(key.clone())
+                .or_insert_with(|| listing.clone());
+        }
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        // Write to a temporary file first so a crash never leaves a truncated cache
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(&data)?)
+            .with_context(|| format!("failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("failed to write {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+/// Newest mtime of `dir` and of every directory below it.
+/// A directory's own mtime only changes with its direct children, so this is what
+/// notices a package replaced anywhere in the tree. Only directories are stat'ed;
+/// a file rewritten in place under the same name goes unnoticed.
+pub fn tree_mtime(dir: &Path) -> Option<SystemTime> {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|entry| entry.file_type().is_dir())
+        .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+        .max()
+}
+
+fn mtime(modified: Option<SystemTime>) -> Option<Mtime> {
+    let since_epoch = modified?.duration_since(UNIX_EPOCH).ok()?;
+    Some((since_epoch.as_secs(), since_epoch.subsec_nanos()))
+}
+
+/// `$XDG_CACHE_HOME/nm-remover/scan-cache.json`, falling back to `~/.cache`
+pub fn default_path() -> Option<PathBuf> {
+    let cache_home = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(cache_home.join("nm-remover").join("scan-cache.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sizes_are_reused_until_mtime_changes() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("scan-cache.json");
+        let nm = temp.path().join("app").join("node_modules");
+        let then = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let size = DirSize {
+            apparent: 10,
+            allocated: 4096,
+            shared: 4096,
+        };
+        let links = vec![SharedLink {
+            id: (1, 42),
+            links: 1,
+            nlink: 2,
+            allocated: 4096,
+        }];
+
+        let cache = ScanCache::load(file.clone());
+        assert_eq!(cache.size(&nm, Some(then)), None);
+        cache.store_size(&nm, Some(then), size, &links);
+        cache.save(&[temp.path().to_path_buf()]).unwrap();
+
+        let cache = ScanCache::load(file.clone());
+        assert_eq!(cache.size(&nm, Some(then)), Some((size, links)));
+        assert_eq!(cache.size(&nm, Some(then + Duration::from_secs(1))), None);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                cached: 1,
+                fresh: 0
+            }
+        );
+
+        assert_eq!(ScanCache::empty(file).size(&nm, Some(then)), None);
+    }
+
+    #[test]
+    fn test_save_replaces_only_records_under_scanned_roots() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("scan-cache.json");
+        let then = Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let work = PathBuf::from("/work/app/node_modules");
+        let home = PathBuf::from("/home/me/site/node_modules");
+
+        let cache = ScanCache::load(file.clone());
+        cache.store_size(&work, then, DirSize::default(), &[]);
+        cache.store_size(&home, then, DirSize::default(), &[]);
+        cache.save(&[PathBuf::from("/")]).unwrap();
+
+        // Re-scanning /work finds nothing there anymore
+        ScanCache::load(file.clone())
+            .save(&[PathBuf::from("/work")])
+            .unwrap();
+
+        let cache = ScanCache::load(file);
+        assert_eq!(cache.size(&work, then), None);
+        assert_eq!(
+            cache.size(&home, then),
+            Some((DirSize::default(), Vec::new()))
+        );
+    }
+
+    #[test]
+    fn test_refresh_keeps_records_of_other_roots() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("scan-cache.json");
+        let then = Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let work = PathBuf::from("/work/app/node_modules");
+        let home = PathBuf::from("/home/me/site/node_modules");
+
+        let cache = ScanCache::load(file.clone());
+        cache.store_size(&work, then, DirSize::default(), &[]);
+        cache.store_size(&home, then, DirSize::default(), &[]);
+        cache.save(&[PathBuf::from("/")]).unwrap();
+
+        let cache = ScanCache::refresh(file.clone());
+        assert_eq!(cache.size(&work, then), None);
+        cache.store_size(&work, then, DirSize::default(), &[]);
+        cache.save(&[PathBuf::from("/work")]).unwrap();
+
+        let cache = ScanCache::load(file);
+        assert_eq!(
+            cache.size(&work, then),
+            Some((DirSize::default(), Vec::new()))
+        );
+        assert_eq!(
+            cache.size(&home, then),
+            Some((DirSize::default(), Vec::new()))
+        );
+    }
+
+    #[test]
+    fn test_tree_mtime_sees_nested_changes() {
+        let temp = tempdir().unwrap();
+        let nm = temp.path().join("node_modules");
+        let pkg = nm.join(".pnpm").join("left-pad@1.3.0");
+        fs::create_dir_all(&pkg).unwrap();
+        let then = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for dir in [&nm, &nm.join(".pnpm"), &pkg] {
+            fs::File::open(dir).unwrap().set_modified(then).unwrap();
+        }
+        assert_eq!(tree_mtime(&nm), Some(then));
+
+        // A package re-installed inside the store leaves node_modules itself untouched
+        let later = then + Duration::from_secs(60);
+        fs::File::open(&pkg).unwrap().set_modified(later).unwrap();
+        assert_eq!(modified_of(&nm), Some(then));
+        assert_eq!(tree_mtime(&nm), Some(later));
+
+        // An update deep inside a package counts as well
+        let deep = pkg.join("node_modules").join("left-pad").join("lib");
+        fs::create_dir_all(&deep).unwrap();
+        let latest = later + Duration::from_secs(60);
+        for dir in deep.ancestors().take_while(|d| *d != pkg) {
+            fs::File::open(dir).unwrap().set_modified(later).unwrap();
+        }
+        fs::File::open(&pkg).unwrap().set_modified(later).unwrap();
+        fs::File::open(&deep).unwrap().set_modified(latest).unwrap();
+        assert_eq!(tree_mtime(&nm), Some(latest));
+    }
+
+    fn modified_of(path: &Path) -> Option<SystemTime> {
+        path.metadata().ok()?.modified().ok()
+    }
+}
@@ -0,0 +1,236 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::detector::kind_names;
+use crate::exclude;
+use crate::filter::{parse_age, parse_size};
+use crate::scanner::DeleteMode;
+use crate::theme::ThemeName;
+
+/// Defaults for command line options. Every field is optional; flags given on
+/// the command line take precedence.
+///
+/// ```toml
+/// roots = ["~/work"]
+/// exclude = [".git", "Library/"]
+/// older-than = "30d"
+/// kind = ["node", "rust"]
+/// delete-mode = "trash"
+/// theme = "light"
+///
+/// [profile.ci]
+/// delete-mode = "permanent"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Settings {
+    /// Directories scanned when no path is given
+    pub roots: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub protect: Option<Vec<String>>,
+    pub one_file_system: Option<bool>,
+    pub include_fs: Option<Vec<String>>,
+    pub follow_symlinks: Option<bool>,
+    pub older_than: Option<String>,
+    pub commit_older_than: Option<String>,
+    pub min_size: Option<String>,
+    pub max_size: Option<String>,
+    pub kind: Option<Vec<String>>,
+    pub delete_mode: Option<DeleteMode>,
+    pub theme: Option<ThemeName>,
+}
+
+impl Settings {
+    /// Field by field, values set in `over` win
+    fn merge(self, over: Settings) -> Settings {
+        Settings {
+            roots: over.roots.or(self.roots),
+            exclude: over.exclude.or(self.exclude),
+            protect: over.protect.or(self.protect),
+            one_file_system: over.one_file_system.or(self.one_file_system),
+            include_fs: over.include_fs.or(self.include_fs),
+            follow_symlinks: over.follow_symlinks.or(self.follow_symlinks),
+            older_than: over.older_than.or(self.older_than),
+            commit_older_than: over.commit_older_than.or(self.commit_older_than),
+            min_size: over.min_size.or(self.min_size),
+            max_size: over.max_size.or(self.max_size),
+            kind: over.kind.or(self.kind),
+            delete_mode: over.delete_mode.or(self.delete_mode),
+            theme: over.theme.or(self.theme),
+        }
+    }
+
+    /// Check values that are only parsed on use, so mistakes surface at startup
+    fn validate(&self) -> Result<()> {
+        self.older_than()?;
+        self.commit_older_than()?;
+        self.min_size()?;
+        self.max_size()?;
+        let kinds = kind_names();
+        for kind in self.kind.iter().flatten() {
+            if !kinds.contains(&kind.as_str()) {
+                bail!(
+                    "unknown kind '{}' (expected one of: {})",
+                    kind,
+                    kinds.join(", ")
+                );
+            }
+        }
+        for pattern in self.exclude.iter().flatten() {
+            exclude::parse_pattern(pattern).map_err(|e| anyhow!(e))?;
+        }
+        Ok(())
+    }
+
+    pub fn older_than(&self) -> Result<Option<Duration>> {
+        self.older_than
+            .as_deref()
+            .map(|age| parse_age(age).map_err(|e| anyhow!("older-than: {}", e)))
+            .transpose()
+    }
+
+    pub fn commit_older_than(&self) -> Result<Option<Duration>> {
+        self.commit_older_than
+            .as_deref()
+            .map(|age| parse_age(age).map_err(|e| anyhow!("commit-older-than: {}", e)))
+            .transpose()
+    }
+
+    pub fn min_size(&self) -> Result<Option<u64>> {
+        self.min_size
+            .as_deref()
+            .map(|size| parse_size(size).map_err(|e| anyhow!("min-size: {}", e)))
+            .transpose()
+    }
+
+    pub fn max_size(&self) -> Result<Option<u64>> {
+        self.max_size
+            .as_deref()
+            .map(|size| parse_size(size).map_err(|e| anyhow!("max-size: {}", e)))
+            .transpose()
+    }
+}
+
+/// Contents of `config.toml`: top-level defaults plus named `[profile.<name>]` overrides
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub defaults: Settings,
+    pub profiles: BTreeMap<String, Settings>,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut table: toml::Table = toml::from_str(text)?;
+        let profiles = match table.remove("profile") {
+            Some(value) => value.try_into().context("invalid [profile] section")?,
+            None => BTreeMap::new(),
+        };
+        let defaults: Settings = toml::Value::Table(table).try_into()?;
+
+        Ok(Self { defaults, profiles })
+    }
+
+    /// Read `path`, or the default location when `None`.
+    /// A missing default file is not an error; a missing `--config` file is.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default())
+            }
+            Err(e) => return Err(e).with_context(|| format!("Cannot read {}", path.display())),
+        };
+
+        Self::parse(&text).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// The defaults with the selected profile applied on top
+    pub fn settings(&self, profile: Option<&str>) -> Result<Settings> {
+        let settings = match profile {
+            None => self.defaults.clone(),
+            Some(name) => {
+                let Some(overrides) = self.profiles.get(name) else {
+                    let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                    bail!(
+                        "unknown profile '{}' (available: {})",
+                        name,
+                        if known.is_empty() {
+                            "none".to_string()
+                        } else {
+                            known.join(", ")
+                        }
+                    );
+                };
+                self.defaults.clone().merge(overrides.clone())
+            }
+        };
+        settings.validate()?;
+        Ok(settings)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/nm-remover/config.toml`, falling back to `~/.config`
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("nm-remover").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+        roots = ["~/work"]
+        exclude = [".git"]
+        older-than = "30d"
+        delete-mode = "trash"
+
+        [profile.ci]
+        delete-mode = "permanent"
+        min-size = "200MB"
+    "#;
+
+    #[test]
+    fn test_profile_overrides_defaults() {
+        let config = Config::parse(SAMPLE).unwrap();
+
+        let base = config.settings(None).unwrap();
+        assert_eq!(base.delete_mode, Some(DeleteMode::Trash));
+        assert_eq!(base.min_size().unwrap(), None);
+
+        let ci = config.settings(Some("ci")).unwrap();
+        assert_eq!(ci.delete_mode, Some(DeleteMode::Permanent));
+        assert_eq!(ci.min_size().unwrap(), Some(200_000_000));
+        assert_eq!(ci.exclude, Some(vec![".git".to_string()]));
+        assert_eq!(
+            ci.older_than().unwrap(),
+            Some(Duration::from_secs(30 * 86400))
+        );
+
+        assert!(config.settings(Some("laptop")).is_err());
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys_and_bad_values() {
+        assert!(Config::parse("older_than = \"30d\"").is_err());
+        assert!(Config::parse("[profile.ci]\nthem = \"mono\"").is_err());
+
+        let config = Config::parse("kind = [\"cobol\"]").unwrap();
+        assert!(config.settings(None).is_err());
+    }
+}
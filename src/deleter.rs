@@ -0,0 +1,276 @@
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use crate::scanner::{delete_node_modules, freed_by_unlink, is_symlink, CancelToken, DeleteMode};
+use crate::space::{FreedSpace, SpaceTracker};
+
+/// Live counters shared between the deletion workers and the UI
+#[derive(Debug, Default)]
+pub struct DeleteProgress {
+    pub files_removed: AtomicU64,
+    pub bytes_removed: AtomicU64,
+    pub current_path: Mutex<String>,
+}
+
+/// A folder queued for deletion: (path, size)
+pub type DeleteItem = (PathBuf, u64);
+
+struct DeleteOutcome {
+    path: PathBuf,
+    result: Result<()>,
+}
+
+/// Result of a finished (or cancelled) deletion job
+#[derive(Debug, Default)]
+pub struct DeleteSummary {
+    pub deleted_paths: Vec<PathBuf>,
+    pub errors: usize,
+    pub skipped: usize,
+    /// Space actually reclaimed per filesystem, measured with statvfs; empty for the trash
+    pub freed: Vec<FreedSpace>,
+}
+
+/// Deletes folders on a pool of background threads so the UI stays responsive.
+/// Cancelling stops workers from picking up new folders; folders already being
+/// removed are finished so nothing is left half-deleted.
+pub struct DeleteJob {
+    pub progress: Arc<DeleteProgress>,
+    pub total: usize,
+    pub total_bytes: u64,
+    cancel: CancelToken,
+    space: SpaceTracker,
+    results: mpsc::Receiver<DeleteOutcome>,
+    workers: Vec<thread::JoinHandle<()>>,
+    summary: DeleteSummary,
+    done: usize,
+}
+
+impl DeleteJob {
+    pub fn start(items: Vec<DeleteItem>, mode: DeleteMode) -> Self {
+        let total = items.len();
+        let total_bytes = items.iter().map(|(_, size)| size).sum();
+        let worker_count = thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .clamp(1, 4)
+            .min(total.max(1));
+
+        // The trash keeps the data on disk, so there is no freed space to measure
+        let space = match mode {
+            DeleteMode::Permanent => SpaceTracker::start(&items),
+            DeleteMode::Trash => SpaceTracker::default(),
+        };
+        let queue = Arc::new(Mutex::new(VecDeque::from(items)));
+        let progress = Arc::new(DeleteProgress::default());
+        let cancel = CancelToken::new();
+        let (tx, rx) = mpsc::channel();
+
+        let workers = (0..worker_count)
+            .map(|_| {
+                let queue = Arc::clone(&queue);
+                let progress = Arc::clone(&progress);
+                let cancel = cancel.clone();
+                let tx = tx.clone();
+
+                thread::spawn(move || loop {
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+                    let next = queue.lock().ok().and_then(|mut q| q.pop_front());
+                    let Some((path, size)) = next else {
+                        break;
+                    };
+
+                    if let Ok(mut current) = progress.current_path.lock() {
+                        *current = path.to_string_lossy().to_string();
+                    }
+
+                    let result = delete_one(&path, size, mode, &progress);
+                    if tx.send(DeleteOutcome { path, result }).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            progress,
+            total,
+            total_bytes,
+            cancel,
+            space,
+            results: rx,
+            workers,
+            summary: DeleteSummary::default(),
+            done: 0,
+        }
+    }
+
+    /// Bytes freed so far, never more than `total_bytes`; files hard-linked
+    /// between the queued folders can make the raw count overshoot
+    pub fn bytes_removed(&self) -> u64 {
+        self.progress
+            .bytes_removed
+            .load(Ordering::Relaxed)
+            .min(self.total_bytes)
+    }
+
+    /// Stop picking up new folders
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Collect finished folders and return how many are done so far
+    pub fn poll(&mut self) -> usize {
+        for outcome in self.results.try_iter() {
+            self.done += 1;
+            match outcome.result {
+                Ok(()) => self.summary.deleted_paths.push(outcome.path),
+                Err(_) => self.summary.errors += 1,
+            }
+        }
+        self.done
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.workers.iter().all(|w| w.is_finished())
+    }
+
+    /// Wait for the workers and return the final tally
+    pub fn finish(mut self) -> DeleteSummary {
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+        self.poll();
+        self.summary.skipped = self.total - self.done;
+        self.summary.freed = self.space.finish(&self.summary.deleted_paths);
+        self.summary
+    }
+}
+
+fn delete_one(path: &Path, size: u64, mode: DeleteMode, progress: &DeleteProgress) -> Result<()> {
+    match mode {
+        DeleteMode::Permanent => remove_dir_with_progress(path, |is_file, bytes| {
+            if is_file {
+                progress.files_removed.fetch_add(1, Ordering::Relaxed);
+            }
+            progress.bytes_removed.fetch_add(bytes, Ordering::Relaxed);
+        }),
+        DeleteMode::Trash => {
+            // Moving to trash is a single rename, so count the whole folder at once
+            delete_node_modules(path, mode)?;
+            progress.bytes_removed.fetch_add(size, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+}
+
+/// Remove a directory tree file by file, calling `on_removed` with whether a file
+/// (rather than a directory) went and the space that freed (see `freed_by_unlink`),
+/// so the bytes add up to the scanned size. A symlink is only unlinked, never followed.
+pub fn remove_dir_with_progress(path: &Path, mut on_removed: impl FnMut(bool, u64)) -> Result<()> {
+    if is_symlink(path) {
+        fs::remove_file(path).with_context(|| format!("Cannot remove {}", path.display()))?;
+        return Ok(());
+    }
+
+    for entry in walkdir::WalkDir::new(path).contents_first(true) {
+        let entry = entry?;
+        let entry_path = entry.path();
+        let freed = entry.metadata().map(|m| freed_by_unlink(&m)).unwrap_or(0);
+
+        if entry.file_type().is_dir() {
+            fs::remove_dir(entry_path)
+                .with_context(|| format!("Cannot remove {}", entry_path.display()))?;
+            on_removed(false, freed);
+        } else {
+            fs::remove_file(entry_path)
+                .with_context(|| format!("Cannot remove {}", entry_path.display()))?;
+            on_removed(true, freed);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_remove_dir_with_progress_counts_files() {
+        let temp = tempdir().unwrap();
+        let nm = temp.path().join("node_modules");
+        fs::create_dir_all(nm.join("a").join("b")).unwrap();
+        fs::write(nm.join("a").join("one.js"), "12345").unwrap();
+        fs::write(nm.join("a").join("b").join("two.js"), "123").unwrap();
+        let expected = scanner::calculate_dir_size(&nm, &CancelToken::new())
+            .0
+            .allocated;
+
+        let mut files = 0;
+        let mut bytes = 0;
+        remove_dir_with_progress(&nm, |is_file, len| {
+            files += u64::from(is_file);
+            bytes += len;
+        })
+        .unwrap();
+
+        assert!(!nm.exists());
+        assert_eq!(files, 2);
+        assert_eq!(bytes, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remove_dir_with_progress_counts_hard_links_once() {
+        let temp = tempdir().unwrap();
+        let nm = temp.path().join("node_modules");
+        fs::create_dir_all(&nm).unwrap();
+        fs::write(nm.join("inside.js"), "x".repeat(10_000)).unwrap();
+        fs::hard_link(nm.join("inside.js"), nm.join("inside-copy.js")).unwrap();
+        fs::write(temp.path().join("store.js"), "x".repeat(10_000)).unwrap();
+        fs::hard_link(temp.path().join("store.js"), nm.join("from-store.js")).unwrap();
+        let expected = scanner::calculate_dir_size(&nm, &CancelToken::new())
+            .0
+            .allocated;
+
+        let mut bytes = 0;
+        remove_dir_with_progress(&nm, |_, len| bytes += len).unwrap();
+
+        // The store's file is still there; the one inside is freed once
+        assert_eq!(bytes, expected);
+        assert!(bytes > 0);
+    }
+
+    #[test]
+    fn test_delete_job_removes_all_items() {
+        let temp = tempdir().unwrap();
+        let items: Vec<DeleteItem> = (0..3)
+            .map(|i| {
+                let nm = temp.path().join(format!("p{}", i)).join("node_modules");
+                fs::create_dir_all(&nm).unwrap();
+                fs::write(nm.join("index.js"), "x").unwrap();
+                (nm, 1)
+            })
+            .collect();
+
+        let job = DeleteJob::start(items, DeleteMode::Permanent);
+        let summary = job.finish();
+
+        assert_eq!(summary.deleted_paths.len(), 3);
+        assert!(!temp.path().join("p0").join("node_modules").exists());
+        assert_eq!(summary.errors, 0);
+        assert_eq!(summary.skipped, 0);
+    }
+}
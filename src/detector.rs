@@ -0,0 +1,211 @@
+use std::path::Path;
+use std::sync::Arc;
+
+/// Recognizes one kind of build artifact directory.
+///
+/// When a detector matches a directory, the scanner reports it and doesn't
+/// descend into it.
+pub trait Detector: Send + Sync {
+    /// Short name shown in the kind column and accepted by `--kind`
+    fn kind(&self) -> &'static str;
+
+    /// Human readable name used in messages, e.g. `node_modules`
+    fn description(&self) -> &'static str;
+
+    /// Whether `dir` is an artifact directory of this kind
+    fn matches(&self, dir: &Path) -> bool;
+}
+
+/// Matches a directory by name, optionally requiring one of several
+/// marker files next to it (e.g. `target` only counts with a sibling `Cargo.toml`).
+#[derive(Debug, Clone)]
+pub struct DirNameDetector {
+    pub kind: &'static str,
+    pub dir_name: &'static str,
+    pub markers: &'static [&'static str],
+}
+
+impl Detector for DirNameDetector {
+    fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    fn description(&self) -> &'static str {
+        self.dir_name
+    }
+
+    fn matches(&self, dir: &Path) -> bool {
+        if dir.file_name().map(|n| n != self.dir_name).unwrap_or(true) {
+            return false;
+        }
+        if self.markers.is_empty() {
+            return true;
+        }
+        let Some(parent) = dir.parent() else {
+            return false;
+        };
+        self.markers.iter().any(|m| parent.join(m).is_file())
+    }
+}
+
+const NODE_MARKERS: &[&str] = &["package.json"];
+
+const PYTHON_MARKERS: &[&str] = &[
+    "pyproject.toml",
+    "requirements.txt",
+    "setup.py",
+    "setup.cfg",
+    "Pipfile",
+];
+
+/// All detectors shipped with nm-remover.
+///
+/// Every kind needs a marker next to it except two whose names are unambiguous:
+/// `node_modules` without a `package.json` is still reported, as orphaned, and
+/// `__pycache__` sits next to the `.py` files of every package, not at a project root.
+pub const BUILTIN_DETECTORS: &[DirNameDetector] = &[
+    DirNameDetector {
+        kind: "node",
+        dir_name: "node_modules",
+        markers: &[],
+    },
+    DirNameDetector {
+        kind: "rust",
+        dir_name: "target",
+        markers: &["Cargo.toml"],
+    },
+    DirNameDetector {
+        kind: "venv",
+        dir_name: ".venv",
+        markers: PYTHON_MARKERS,
+    },
+    DirNameDetector {
+        kind: "pycache",
+        dir_name: "__pycache__",
+        markers: &[],
+    },
+    DirNameDetector {
+        kind: "next",
+        dir_name: ".next",
+        markers: NODE_MARKERS,
+    },
+    DirNameDetector {
+        kind: "nuxt",
+        dir_name: ".nuxt",
+        markers: NODE_MARKERS,
+    },
+    DirNameDetector {
+        kind: "turbo",
+        dir_name: ".turbo",
+        markers: &["package.json", "turbo.json"],
+    },
+    DirNameDetector {
+        kind: "parcel",
+        dir_name: ".parcel-cache",
+        markers: &["package.json", ".parcelrc"],
+    },
+    DirNameDetector {
+        kind: "dist",
+        dir_name: "dist",
+        markers: NODE_MARKERS,
+    },
+    DirNameDetector {
+        kind: "gradle",
+        dir_name: ".gradle",
+        markers: &[
+            "build.gradle",
+            "build.gradle.kts",
+            "settings.gradle",
+            "settings.gradle.kts",
+        ],
+    },
+    DirNameDetector {
+        kind: "pods",
+        dir_name: "Pods",
+        markers: &["Podfile"],
+    },
+];
+
+/// Values accepted by `--kind`: every built-in kind and `all`
+pub fn kind_names() -> Vec<&'static str> {
+    std::iter::once("all")
+        .chain(BUILTIN_DETECTORS.iter().map(|d| d.kind))
+        .collect()
+}
+
+/// Directories of installed packages; they never hold projects, with or without a marker
+const PACKAGE_DIRS: &[&str] = &["node_modules", ".venv"];
+
+/// Whether `dir` is an artifact of any built-in kind, selected or not. The
+/// scanner never descends into one: a `dist` inside `node_modules` belongs to
+/// an installed package, not to a project.
+pub fn is_builtin_artifact(dir: &Path) -> bool {
+    let in_packages = dir
+        .file_name()
+        .is_some_and(|name| PACKAGE_DIRS.iter().any(|p| name == *p));
+    in_packages || BUILTIN_DETECTORS.iter().any(|d| d.matches(dir))
+}
+
+/// Detector used when no `--kind` is given
+pub fn default_detectors() -> Vec<Arc<dyn Detector>> {
+    detectors_for(&["node"])
+}
+
+/// Built-in detectors for the given kind names; `all` selects every detector
+pub fn detectors_for<S: AsRef<str>>(kinds: &[S]) -> Vec<Arc<dyn Detector>> {
+    let all = kinds.iter().any(|k| k.as_ref() == "all");
+    BUILTIN_DETECTORS
+        .iter()
+        .filter(|d| all || kinds.iter().any(|k| k.as_ref() == d.kind))
+        .map(|d| Arc::new(d.clone()) as Arc<dyn Detector>)
+        .collect()
+}
+
+/// Name for what a scan looks for, e.g. `node_modules` or `node_modules, target`
+pub fn describe(detectors: &[Arc<dyn Detector>]) -> String {
+    if detectors.is_empty() || detectors.len() > 3 {
+        return "build artifact".to_string();
+    }
+    detectors
+        .iter()
+        .map(|d| d.description())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_marker_file_is_required() {
+        let temp = tempdir().unwrap();
+        let target = temp.path().join("target");
+        fs::create_dir_all(&target).unwrap();
+
+        let rust = &detectors_for(&["rust"])[0];
+        assert!(!rust.matches(&target));
+
+        fs::write(temp.path().join("Cargo.toml"), "[package]").unwrap();
+        assert!(rust.matches(&target));
+    }
+
+    #[test]
+    fn test_only_unambiguous_kinds_skip_markers() {
+        let unmarked: Vec<&str> = BUILTIN_DETECTORS
+            .iter()
+            .filter(|d| d.markers.is_empty())
+            .map(|d| d.kind)
+            .collect();
+        assert_eq!(unmarked, ["node", "pycache"]);
+    }
+
+    #[test]
+    fn test_detectors_for_all_kinds() {
+        assert_eq!(detectors_for(&["all"]).len(), BUILTIN_DETECTORS.len());
+        assert_eq!(default_detectors()[0].kind(), "node");
+        assert_eq!(kind_names().len(), BUILTIN_DETECTORS.len() + 1);
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::{anyhow, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Per-directory ignore file, read at every level of the scan
+pub const IGNORE_FILE: &str = ".nmignore";
+
+/// Gitignore-style rules that hide directories from the scanner.
+/// Excluded directories are neither reported nor descended into.
+#[derive(Clone, Default)]
+pub struct ExcludeRules {
+    /// `--exclude` patterns; always win over `.nmignore` files
+    cli: Option<Arc<Gitignore>>,
+    /// `.nmignore` files from the scan root down to the current directory
+    layers: Vec<Arc<Gitignore>>,
+}
+
+impl ExcludeRules {
+    /// Rules for a scan of `root`: `patterns` are anchored at `root` like a
+    /// top-level `.gitignore`, and `root/.nmignore` is picked up if present.
+    pub fn new(root: &Path, patterns: &[String]) -> Result<Self> {
+        let cli = if patterns.is_empty() {
+            None
+        } else {
+            let mut builder = GitignoreBuilder::new(root);
+            for pattern in patterns {
+                builder
+                    .add_line(None, pattern)
+                    .map_err(|e| anyhow!("invalid exclude pattern '{}': {}", pattern, e))?;
+            }
+            Some(Arc::new(builder.build()?))
+        };
+
+        Ok(Self {
+            cli,
+            layers: Vec::new(),
+        }
+        .enter(root))
+    }
+
+    /// Rules for the contents of `dir`, adding `dir/.nmignore` on top if it exists
+    pub fn enter(&self, dir: &Path) -> Self {
+        let file = dir.join(IGNORE_FILE);
+        if !file.is_file() {
+            return self.clone();
+        }
+
+        let mut builder = GitignoreBuilder::new(dir);
+        // Bad lines are skipped; the rest of the file still applies
+        let _ = builder.add(&file);
+        let mut rules = self.clone();
+        if let Ok(gitignore) = builder.build() {
+            rules.layers.push(Arc::new(gitignore));
+        }
+        rules
+    }
+
+    /// Whether the directory at `path` should be skipped.
+    /// The closest `.nmignore` with a matching rule decides, so `!pattern` can re-include.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        if let Some(cli) = &self.cli {
+            if cli.matched(path, true).is_ignore() {
+                return true;
+            }
+        }
+
+        for layer in self.layers.iter().rev() {
+            match layer.matched(path, true) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+/// Check an `--exclude` pattern so mistakes are reported before scanning
+pub fn parse_pattern(input: &str) -> Result<String, String> {
+    GitignoreBuilder::new("/")
+        .add_line(None, input)
+        .map_err(|e| format!("invalid exclude pattern '{}': {}", input, e))?;
+    Ok(input.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_cli_patterns_and_nested_nmignore() {
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        let app = root.join("app");
+        fs::create_dir_all(&app).unwrap();
+        fs::write(app.join(IGNORE_FILE), "fixtures/\n!keep-me\n").unwrap();
+        fs::write(root.join(IGNORE_FILE), "keep-me\n").unwrap();
+
+        let rules = ExcludeRules::new(root, &[".git".to_string()]).unwrap();
+        assert!(rules.is_excluded(&root.join(".git")));
+        assert!(rules.is_excluded(&root.join("keep-me")));
+        assert!(!rules.is_excluded(&app));
+
+        let in_app = rules.enter(&app);
+        assert!(in_app.is_excluded(&app.join("fixtures")));
+        assert!(in_app.is_excluded(&app.join(".git")));
+        // The closer .nmignore re-includes what the root one excluded
+        assert!(!in_app.is_excluded(&app.join("keep-me")));
+    }
+}
@@ -0,0 +1,222 @@
+use bytesize::ByteSize;
+use globset::{GlobBuilder, GlobMatcher};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::scanner::NodeModulesEntry;
+
+/// Criteria used to narrow down scan results before they are listed or deleted
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter {
+    pub older_than: Option<Duration>,
+    /// Only entries whose git HEAD commit is at least this old
+    pub commit_older_than: Option<Duration>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+}
+
+impl EntryFilter {
+    pub fn is_empty(&self) -> bool {
+        self.older_than.is_none()
+            && self.commit_older_than.is_none()
+            && self.min_size.is_none()
+            && self.max_size.is_none()
+    }
+
+    pub fn matches(&self, entry: &NodeModulesEntry) -> bool {
+        if let Some(min) = self.min_size {
+            if entry.size < min {
+                return false;
+            }
+        }
+
+        if let Some(max) = self.max_size {
+            if entry.size > max {
+                return false;
+            }
+        }
+
+        if let Some(age) = self.older_than {
+            // Judged by project activity; entries with no known time can't be proven old enough
+            let old_enough = entry
+                .stale_since()
+                .and_then(|t| t.elapsed().ok())
+                .map(|elapsed| elapsed >= age)
+                .unwrap_or(false);
+            if !old_enough {
+                return false;
+            }
+        }
+
+        if let Some(age) = self.commit_older_than {
+            // Projects outside of git, or without commits, have no commit date to compare
+            let old_enough = entry
+                .git
+                .as_ref()
+                .and_then(|git| git.last_commit)
+                .and_then(|t| t.elapsed().ok())
+                .map(|elapsed| elapsed >= age)
+                .unwrap_or(false);
+            if !old_enough {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn apply(&self, entries: Vec<NodeModulesEntry>) -> Vec<NodeModulesEntry> {
+        if self.is_empty() {
+            return entries;
+        }
+        entries.into_iter().filter(|e| self.matches(e)).collect()
+    }
+}
+
+/// Interactive path filter typed in the TUI.
+/// Plain text matches as a case-insensitive substring; text containing `*`, `?` or `[`
+/// is treated as a glob that may match anywhere in the path.
+#[derive(Debug, Clone)]
+pub enum PathQuery {
+    Substring(String),
+    Glob(GlobMatcher),
+}
+
+impl PathQuery {
+    pub fn new(query: &str) -> Self {
+        if query.contains(['*', '?', '[']) {
+            let pattern = format!("*{}*", query.trim_matches('*'));
+            if let Ok(glob) = GlobBuilder::new(&pattern).case_insensitive(true).build() {
+                return PathQuery::Glob(glob.compile_matcher());
+            }
+        }
+        PathQuery::Substring(query.to_lowercase())
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        match self {
+            PathQuery::Substring(text) => path.to_string_lossy().to_lowercase().contains(text),
+            PathQuery::Glob(glob) => glob.is_match(path),
+        }
+    }
+}
+
+/// Parse an age such as `30d`, `12h` or `2w`.
+/// Supported units: s, m (minutes), h, d, w, y.
+pub fn parse_age(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+
+    let value: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid age '{}': expected a number like 30d", input))?;
+
+    let multiplier = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "" | "d" => 86400,
+        "w" => 7 * 86400,
+        "y" => 365 * 86400,
+        other => {
+            return Err(format!(
+                "invalid age unit '{}': use s, m, h, d, w or y",
+                other
+            ))
+        }
+    };
+
+    let seconds = value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("invalid age '{}': too large", input))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parse a size with human units such as `200MB` or `1.5GiB`.
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    input
+        .trim()
+        .parse::<ByteSize>()
+        .map(|size| size.as_u64())
+        .map_err(|e| format!("invalid size '{}': {}", input, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::GitInfo;
+    use std::path::PathBuf;
+    use std::time::SystemTime;
+
+    fn entry(size: u64, age_secs: u64) -> NodeModulesEntry {
+        NodeModulesEntry {
+            path: PathBuf::from("/tmp/project/node_modules"),
+            size,
+            last_modified: Some(SystemTime::now() - Duration::from_secs(age_secs)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_age_units() {
+        assert_eq!(parse_age("30d").unwrap(), Duration::from_secs(30 * 86400));
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 3600));
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * 86400));
+        assert!(parse_age("abc").is_err());
+        assert!(parse_age("5x").is_err());
+    }
+
+    #[test]
+    fn test_parse_age_rejects_overflow() {
+        assert!(parse_age("99999999999999999y").is_err());
+        assert!(parse_age("99999999999999999999d").is_err());
+    }
+
+    #[test]
+    fn test_path_query_substring_and_glob() {
+        let path = PathBuf::from("/home/me/Work/api-server/node_modules");
+
+        assert!(PathQuery::new("work/api").matches(&path));
+        assert!(!PathQuery::new("frontend").matches(&path));
+        assert!(PathQuery::new("api-*").matches(&path));
+        assert!(PathQuery::new("/home/*/work").matches(&path));
+        assert!(!PathQuery::new("web*").matches(&path));
+    }
+
+    #[test]
+    fn test_filter_by_size_and_age() {
+        let filter = EntryFilter {
+            older_than: Some(parse_age("30d").unwrap()),
+            commit_older_than: None,
+            min_size: Some(parse_size("200MB").unwrap()),
+            max_size: Some(parse_size("1GB").unwrap()),
+        };
+
+        assert!(filter.matches(&entry(500_000_000, 40 * 86400)));
+        assert!(!filter.matches(&entry(500_000_000, 86400)));
+        assert!(!filter.matches(&entry(100_000_000, 40 * 86400)));
+        assert!(!filter.matches(&entry(2_000_000_000, 40 * 86400)));
+    }
+
+    #[test]
+    fn test_filter_by_last_commit() {
+        let filter = EntryFilter {
+            commit_older_than: Some(parse_age("30d").unwrap()),
+            ..Default::default()
+        };
+        let committed = |age_secs| NodeModulesEntry {
+            git: Some(GitInfo {
+                last_commit: Some(SystemTime::now() - Duration::from_secs(age_secs)),
+                ..Default::default()
+            }),
+            ..entry(1, 0)
+        };
+
+        assert!(filter.matches(&committed(40 * 86400)));
+        assert!(!filter.matches(&committed(86400)));
+        assert!(!filter.matches(&entry(1, 40 * 86400)));
+    }
+}
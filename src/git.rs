@@ -0,0 +1,206 @@
+use git2::{Repository, StatusOptions};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Git state of the project owning an artifact, read from the local `.git`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitInfo {
+    /// Commit time of HEAD; `None` in a repository without commits
+    pub last_commit: Option<SystemTime>,
+    /// The project has uncommitted changes or untracked files
+    pub dirty: bool,
+    /// The artifact directory itself is committed to the repository
+    pub tracked: bool,
+}
+
+/// Repositories opened during one scan, so a monorepo with many workspaces is
+/// opened and status-walked once rather than once per artifact
+#[derive(Default)]
+pub struct GitRepos {
+    /// Keyed by the directory holding `.git`; `None` when it couldn't be opened
+    repos: Mutex<HashMap<PathBuf, Arc<OnceLock<Option<RepoState>>>>>,
+}
+
+/// What is needed from one repository, read once
+struct RepoState {
+    workdir: PathBuf,
+    last_commit: Option<SystemTime>,
+    /// Workdir-relative paths that differ from HEAD; untracked directories are
+    /// listed once, not file by file
+    changed: Vec<PathBuf>,
+    /// `git2::Repository` can't be shared between the scan threads as is
+    repo: Mutex<Repository>,
+}
+
+impl GitRepos {
+    /// Git state of the project containing `artifact`. Returns `None` outside of
+    /// a repository and for bare repositories.
+    pub fn info(&self, artifact: &Path) -> Option<GitInfo> {
+        let project = artifact.parent()?.canonicalize().ok()?;
+        let top = project.ancestors().find(|dir| dir.join(".git").exists())?;
+        let slot = self
+            .repos
+            .lock()
+            .ok()?
+            .entry(top.to_path_buf())
+            .or_default()
+            .clone();
+        let repo = slot.get_or_init(|| RepoState::read(top)).as_ref()?;
+
+        let artifact = project.join(artifact.file_name()?);
+        let relative = artifact.strip_prefix(&repo.workdir).ok()?;
+        Some(GitInfo {
+            last_commit: repo.last_commit,
+            dirty: repo.is_dirty(relative.parent().unwrap_or(Path::new(""))),
+            tracked: repo.is_tracked(relative),
+        })
+    }
+}
+
+impl RepoState {
+    fn read(top: &Path) -> Option<RepoState> {
+        let repo = Repository::open(top).ok()?;
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+        let last_commit = repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .ok()
+            .map(|commit| UNIX_EPOCH + Duration::from_secs(commit.time().seconds().max(0) as u64));
+
+        Some(RepoState {
+            workdir,
+            last_commit,
+            changed: changed_paths(&repo),
+            repo: Mutex::new(repo),
+        })
+    }
+
+    /// Whether anything under `project` (relative to the workdir, empty for the
+    /// whole repository) differs from HEAD, ignoring ignored files
+    fn is_dirty(&self, project: &Path) -> bool {
+        // An untracked directory holding the whole project counts too
+        self.changed
+            .iter()
+            .any(|path| path.starts_with(project) || project.starts_with(path))
+    }
+
+    /// Whether the index has the artifact itself (a committed symlink) or any file below it
+    fn is_tracked(&self, relative: &Path) -> bool {
+        let Ok(repo) = self.repo.lock() else {
+            return false;
+        };
+        // libgit2 keeps the index loaded; this only re-reads it when the file changed
+        let (Ok(index), Some(path)) = (repo.index(), relative.to_str()) else {
+            return false;
+        };
+        index.get_path(relative, 0).is_some() || index.find_prefix(format!("{}/", path)).is_ok()
+    }
+}
+
+/// Paths with uncommitted changes or untracked files, from one status walk
+fn changed_paths(repo: &Repository) -> Vec<PathBuf> {
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .exclude_submodules(true);
+    let Ok(statuses) = repo.statuses(Some(&mut options)) else {
+        return Vec::new();
+    };
+    statuses
+        .iter()
+        .filter_map(|entry| entry.path().map(PathBuf::from))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn commit_all(repo: &Repository) {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::FORCE, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_clean_repo_with_ignored_node_modules() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        fs::write(temp.path().join("package.json"), "{}").unwrap();
+        fs::write(temp.path().join(".gitignore"), "node_modules/\n").unwrap();
+        commit_all(&repo);
+        let nm = temp.path().join("node_modules");
+        fs::create_dir_all(nm.join("dep")).unwrap();
+        fs::write(nm.join("dep").join("index.js"), "").unwrap();
+
+        let info = GitRepos::default().info(&nm).unwrap();
+
+        assert!(info.last_commit.is_some());
+        assert!(!info.dirty);
+        assert!(!info.tracked);
+
+        fs::write(temp.path().join("index.js"), "").unwrap();
+        // A new scan sees the change
+        assert!(GitRepos::default().info(&nm).unwrap().dirty);
+    }
+
+    #[test]
+    fn test_committed_node_modules_is_tracked() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let nm = temp.path().join("app").join("node_modules");
+        fs::create_dir_all(nm.join("dep")).unwrap();
+        fs::write(nm.join("dep").join("index.js"), "").unwrap();
+        commit_all(&repo);
+
+        assert!(GitRepos::default().info(&nm).unwrap().tracked);
+
+        let outside = tempdir().unwrap();
+        assert_eq!(
+            GitRepos::default().info(&outside.path().join("node_modules")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_workspaces_share_one_repository() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        fs::write(temp.path().join(".gitignore"), "node_modules/\n").unwrap();
+        for name in ["a", "b", "c"] {
+            fs::create_dir_all(temp.path().join("packages").join(name)).unwrap();
+            fs::write(
+                temp.path().join("packages").join(name).join("package.json"),
+                "{}",
+            )
+            .unwrap();
+        }
+        commit_all(&repo);
+        fs::write(temp.path().join("packages/a/index.js"), "").unwrap();
+        fs::create_dir_all(temp.path().join("packages/new/src")).unwrap();
+        fs::write(temp.path().join("packages/new/src/index.js"), "").unwrap();
+
+        let repos = GitRepos::default();
+        let info = |name: &str| {
+            let nm = temp.path().join("packages").join(name).join("node_modules");
+            repos.info(&nm).unwrap()
+        };
+        assert!(info("a").dirty);
+        assert!(!info("b").dirty);
+        assert!(!info("c").dirty);
+        // Inside an untracked directory
+        assert!(info("new").dirty);
+        assert_eq!(repos.repos.lock().unwrap().len(), 1);
+    }
+}
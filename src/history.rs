@@ -0,0 +1,456 @@
+use anyhow::{Context, Result};
+use bytesize::ByteSize;
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::scanner::{DeleteMode, NodeModulesEntry};
+
+/// An artifact as it was when a scan found it or it was deleted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedEntry {
+    pub path: String,
+    pub kind: String,
+    pub size: u64,
+}
+
+impl RecordedEntry {
+    pub fn new(entry: &NodeModulesEntry) -> Self {
+        Self {
+            path: entry.path.to_string_lossy().to_string(),
+            kind: entry.kind.to_string(),
+            size: entry.size,
+        }
+    }
+}
+
+/// One line of the history file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum HistoryEvent {
+    Scan {
+        /// Seconds since the epoch
+        time: u64,
+        roots: Vec<String>,
+        entries: Vec<RecordedEntry>,
+    },
+    Delete {
+        time: u64,
+        /// `permanent` or `trash`
+        mode: String,
+        deleted: Vec<RecordedEntry>,
+        /// Measured with statvfs; `None` for the trash, which frees nothing yet
+        freed: Option<u64>,
+    },
+}
+
+impl HistoryEvent {
+    pub fn scan(roots: &[PathBuf], entries: &[NodeModulesEntry]) -> Self {
+        HistoryEvent::Scan {
+            time: now(),
+            roots: roots
+                .iter()
+                .map(|root| root.to_string_lossy().to_string())
+                .collect(),
+            entries: entries.iter().map(RecordedEntry::new).collect(),
+        }
+    }
+
+    pub fn delete(mode: DeleteMode, deleted: Vec<RecordedEntry>, freed: Option<u64>) -> Self {
+        let mode = match mode {
+            DeleteMode::Permanent => "permanent",
+            DeleteMode::Trash => "trash",
+        };
+        HistoryEvent::Delete {
+            time: now(),
+            mode: mode.to_string(),
+            deleted,
+            freed,
+        }
+    }
+
+    fn time(&self) -> u64 {
+        match self {
+            HistoryEvent::Scan { time, .. } | HistoryEvent::Delete { time, .. } => *time,
+        }
+    }
+
+    fn is_trash(&self) -> bool {
+        matches!(self, HistoryEvent::Delete { mode, .. } if mode == "trash")
+    }
+
+    /// Bytes this deletion gave back: measured when known, else the sum of the sizes.
+    /// Moving to the trash gives nothing back until the trash is emptied.
+    fn bytes_freed(&self) -> u64 {
+        match self {
+            HistoryEvent::Scan { .. } => 0,
+            _ if self.is_trash() => 0,
+            HistoryEvent::Delete { deleted, freed, .. } => {
+                freed.unwrap_or_else(|| deleted.iter().map(|e| e.size).sum())
+            }
+        }
+    }
+
+    /// Bytes this deletion moved to the trash
+    fn bytes_trashed(&self) -> u64 {
+        match self {
+            HistoryEvent::Delete { deleted, .. } if self.is_trash() => {
+                deleted.iter().map(|e| e.size).sum()
+            }
+            _ => 0,
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Append-only JSON-lines log of scans and deletions
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    /// The history file at the default location
+    pub fn open() -> Option<History> {
+        default_path().map(History::at)
+    }
+
+    pub fn at(path: PathBuf) -> History {
+        History { path }
+    }
+
+    pub fn append(&self, event: &HistoryEvent) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("failed to open {}", self.path.display()))?;
+        // One write per line keeps concurrent runs from interleaving records
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        Ok(())
+    }
+
+    /// Every recorded event in order; lines that can't be parsed are skipped
+    pub fn read(&self) -> Result<Vec<HistoryEvent>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to read {}", self.path.display()))
+            }
+        };
+        Ok(text
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Add `event` to the default history file. History is a nice-to-have, so a
+/// missing or unwritable data directory doesn't fail the scan or deletion.
+pub fn record(event: HistoryEvent) {
+    if let Some(history) = History::open() {
+        let _ = history.append(&event);
+    }
+}
+
+/// `$XDG_DATA_HOME/nm-remover/history.jsonl`, falling back to `~/.local/share`
+pub fn default_path() -> Option<PathBuf> {
+    let data_home = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(data_home.join("nm-remover").join("history.jsonl"))
+}
+
+/// Activity in one ISO week
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WeekTotal {
+    /// e.g. `2026-W42`
+    pub week: String,
+    pub scans: usize,
+    pub deleted: usize,
+    pub freed: u64,
+    /// Bytes moved to the trash, which are not counted as freed
+    pub trashed: u64,
+    /// Bytes freed in this and all earlier weeks
+    pub cumulative: u64,
+}
+
+/// A folder that was deleted and later found again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Offender {
+    pub path: String,
+    /// How many times it was found again after being deleted
+    pub returns: usize,
+    /// Bytes freed by all its deletions
+    pub freed: u64,
+}
+
+/// Summary of the history file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub scans: usize,
+    pub deleted: usize,
+    pub freed: u64,
+    pub trashed: u64,
+    /// Oldest week first
+    pub weeks: Vec<WeekTotal>,
+    /// Most returns first
+    pub offenders: Vec<Offender>,
+}
+
+impl Report {
+    pub fn new(events: &[HistoryEvent]) -> Self {
+        let mut weeks: BTreeMap<(i32, u32), WeekTotal> = BTreeMap::new();
+        let mut report = Report::default();
+        // Paths deleted and not seen since, and how often each came back
+        let mut gone: HashSet<&str> = HashSet::new();
+        let mut offenders: HashMap<&str, Offender> = HashMap::new();
+
+        for event in events {
+            let date = DateTime::<Utc>::from(UNIX_EPOCH + Duration::from_secs(event.time()));
+            let iso = date.iso_week();
+            let week = weeks
+                .entry((iso.year(), iso.week()))
+                .or_insert_with(|| WeekTotal {
+                    week: format!("{}-W{:02}", iso.year(), iso.week()),
+                    ..WeekTotal::default()
+                });
+
+            match event {
+                HistoryEvent::Scan { entries, .. } => {
+                    week.scans += 1;
+                    report.scans += 1;
+                    for entry in entries {
+                        if gone.remove(entry.path.as_str()) {
+                            offenders
+                                .entry(&entry.path)
+                                .or_insert_with(|| Offender {
+                                    path: entry.path.clone(),
+                                    returns: 0,
+                                    freed: 0,
+                                })
+                                .returns += 1;
+                        }
+                    }
+                }
+                HistoryEvent::Delete { deleted, .. } => {
+                    let freed = event.bytes_freed();
+                    let trashed = event.bytes_trashed();
+                    week.deleted += deleted.len();
+                    week.freed += freed;
+                    week.trashed += trashed;
+                    report.deleted += deleted.len();
+                    report.freed += freed;
+                    report.trashed += trashed;
+                    for entry in deleted {
+                        gone.insert(&entry.path);
+                        offenders
+                            .entry(&entry.path)
+                            .or_insert_with(|| Offender {
+                                path: entry.path.clone(),
+                                returns: 0,
+                                freed: 0,
+                            })
+                            .freed += if event.is_trash() { 0 } else { entry.size };
+                    }
+                }
+            }
+        }
+
+        let mut cumulative = 0;
+        report.weeks = weeks
+            .into_values()
+            .map(|mut week| {
+                cumulative += week.freed;
+                week.cumulative = cumulative;
+                week
+            })
+            .collect();
+
+        report.offenders = offenders.into_values().filter(|o| o.returns > 0).collect();
+        report.offenders.sort_by(|a, b| {
+            b.returns
+                .cmp(&a.returns)
+                .then(b.freed.cmp(&a.freed))
+                .then(a.path.cmp(&b.path))
+        });
+        report
+    }
+
+    /// The report as text: totals, the last `weeks` weeks and the `top` worst offenders
+    pub fn lines(&self, weeks: usize, top: usize) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{} scans, {} folders deleted, {} freed in total, {} moved to the trash",
+            self.scans,
+            self.deleted,
+            ByteSize::b(self.freed),
+            ByteSize::b(self.trashed)
+        )];
+
+        lines.push(String::new());
+        lines.push(format!(
+            "{:<10} {:>6} {:>8} {:>12} {:>12} {:>12}",
+            "Week", "Scans", "Deleted", "Freed", "Trashed", "Cumulative"
+        ));
+        let skip = self.weeks.len().saturating_sub(weeks);
+        for week in &self.weeks[skip..] {
+            lines.push(format!(
+                "{:<10} {:>6} {:>8} {:>12} {:>12} {:>12}",
+                week.week,
+                week.scans,
+                week.deleted,
+                ByteSize::b(week.freed).to_string(),
+                ByteSize::b(week.trashed).to_string(),
+                ByteSize::b(week.cumulative).to_string()
+            ));
+        }
+
+        lines.push(String::new());
+        if self.offenders.is_empty() {
+            lines.push("No folders have come back after being deleted.".to_string());
+        } else {
+            lines.push("Keep coming back:".to_string());
+            for offender in self.offenders.iter().take(top) {
+                lines.push(format!(
+                    "  {:>3}x  {:>10}  {}",
+                    offender.returns,
+                    ByteSize::b(offender.freed).to_string(),
+                    offender.path
+                ));
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn recorded(path: &str, size: u64) -> RecordedEntry {
+        RecordedEntry {
+            path: path.to_string(),
+            kind: "node".to_string(),
+            size,
+        }
+    }
+
+    fn scan(time: u64, paths: &[&str]) -> HistoryEvent {
+        HistoryEvent::Scan {
+            time,
+            roots: vec!["/work".to_string()],
+            entries: paths.iter().map(|p| recorded(p, 100)).collect(),
+        }
+    }
+
+    fn delete(time: u64, paths: &[&str], freed: Option<u64>) -> HistoryEvent {
+        HistoryEvent::Delete {
+            time,
+            mode: "permanent".to_string(),
+            deleted: paths.iter().map(|p| recorded(p, 100)).collect(),
+            freed,
+        }
+    }
+
+    fn trash(time: u64, paths: &[&str]) -> HistoryEvent {
+        HistoryEvent::Delete {
+            time,
+            mode: "trash".to_string(),
+            deleted: paths.iter().map(|p| recorded(p, 100)).collect(),
+            freed: None,
+        }
+    }
+
+    #[test]
+    fn test_append_and_read_back() {
+        let temp = tempdir().unwrap();
+        let history = History::at(temp.path().join("nested").join("history.jsonl"));
+        assert!(history.read().unwrap().is_empty());
+
+        let events = vec![scan(1, &["/work/a"]), delete(2, &["/work/a"], Some(90))];
+        for event in &events {
+            history.append(event).unwrap();
+        }
+        fs::write(
+            history.path(),
+            format!("{}not json\n", fs::read_to_string(history.path()).unwrap()),
+        )
+        .unwrap();
+
+        assert_eq!(history.read().unwrap(), events);
+    }
+
+    #[test]
+    fn test_report_weeks_and_repeat_offenders() {
+        // 2023-11-14 (week 46) and a week later
+        let week1 = 1_700_000_000;
+        let week2 = week1 + 7 * 86400;
+        let events = vec![
+            scan(week1, &["/work/a", "/work/b"]),
+            delete(week1 + 60, &["/work/a", "/work/b"], Some(150)),
+            scan(week2, &["/work/a"]),
+            delete(week2 + 60, &["/work/a"], None),
+            scan(week2 + 120, &["/work/a"]),
+        ];
+
+        let report = Report::new(&events);
+
+        assert_eq!(report.scans, 3);
+        assert_eq!(report.deleted, 3);
+        assert_eq!(report.freed, 250);
+        assert_eq!(report.weeks.len(), 2);
+        assert_eq!(report.weeks[0].week, "2023-W46");
+        assert_eq!(report.weeks[0].freed, 150);
+        assert_eq!(report.weeks[1].cumulative, 250);
+        assert_eq!(
+            report.offenders,
+            vec![Offender {
+                path: "/work/a".to_string(),
+                returns: 2,
+                freed: 200,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_trash_is_not_counted_as_freed() {
+        let events = vec![
+            delete(1_700_000_000, &["/work/a"], Some(120)),
+            trash(1_700_000_060, &["/work/b", "/work/c"]),
+            scan(1_700_000_120, &["/work/b"]),
+        ];
+
+        let report = Report::new(&events);
+
+        assert_eq!(report.deleted, 3);
+        assert_eq!(report.freed, 120);
+        assert_eq!(report.trashed, 200);
+        assert_eq!(report.weeks[0].freed, 120);
+        assert_eq!(report.weeks[0].trashed, 200);
+        assert_eq!(report.weeks[0].cumulative, 120);
+        assert_eq!(report.offenders[0].path, "/work/b");
+        assert_eq!(report.offenders[0].freed, 0);
+    }
+}
@@ -1,221 +1,1005 @@
+mod activity;
+mod cache;
+mod config;
+mod deleter;
+mod detector;
+mod exclude;
+mod filter;
+mod git;
+mod history;
+mod mounts;
+mod output;
+mod procs;
+mod project;
 mod scanner;
+mod sort;
+mod space;
+mod theme;
+mod trash;
 mod ui;
+mod watch;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{DisableBracketedPaste, EnableBracketedPaste},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::prelude::*;
+use std::collections::HashSet;
 use std::io::stdout;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
-use scanner::{delete_node_modules, scan_for_node_modules, NodeModulesEntry, ProgressCallback};
+use cache::{CacheMode, CacheStats, ScanCache};
+use config::Config;
+use deleter::{DeleteItem, DeleteJob, DeleteSummary};
+use filter::{parse_age, parse_size, EntryFilter};
+use history::{History, HistoryEvent, RecordedEntry, Report};
+use output::{ListWriter, OutputFormat};
+use procs::ProcessTable;
+use scanner::{
+    combined_size, delete_node_modules, normalize_roots, scan_roots, CancelToken, DeleteMode,
+    EntryCallback, NodeModulesEntry, ProgressCallback, ScanOptions,
+};
+use sort::{sort_entries, SortKey};
+use space::SpaceTracker;
+use theme::ThemeName;
 use ui::{draw, draw_welcome, handle_input, handle_welcome_input, App, AppMode};
+use watch::LiveWatcher;
 
 #[derive(Parser, Debug)]
 #[command(
     name = "nm-remover",
     about = "Find and remove node_modules folders",
     version,
-    author
+    author,
+    args_conflicts_with_subcommands = true
 )]
 struct Args {
-    /// Directory to scan for node_modules (optional - will show input prompt if not provided)
-    path: Option<PathBuf>,
+    /// Directories to scan for node_modules (optional - will show input prompt if not provided)
+    paths: Vec<PathBuf>,
 
     /// Just list node_modules without interactive UI
     #[arg(short, long)]
     list: bool,
 
+    /// Sort order for the list (size: largest first, age: oldest first, path: A-Z)
+    #[arg(long, value_enum, default_value_t = SortKey::Size)]
+    sort: SortKey,
+
+    /// Reverse the sort order
+    #[arg(long)]
+    reverse: bool,
+
+    /// Artifact kinds to look for, comma separated (default: node)
+    #[arg(
+        long,
+        value_name = "KIND",
+        value_delimiter = ',',
+        value_parser = clap::builder::PossibleValuesParser::new(detector::kind_names())
+    )]
+    kind: Vec<String>,
+
+    /// Skip directories matching this gitignore-style pattern (repeatable).
+    /// `.nmignore` files in scanned directories are honored as well
+    #[arg(long, value_name = "GLOB", value_parser = exclude::parse_pattern)]
+    exclude: Vec<String>,
+
+    /// Don't descend into directories on other filesystems than the scanned path
+    #[arg(short = 'x', long, overrides_with = "no_one_file_system")]
+    one_file_system: bool,
+
+    /// Descend into other filesystems even if the config file sets one_file_system
+    #[arg(long, overrides_with = "one_file_system")]
+    no_one_file_system: bool,
+
+    /// Scan these filesystem types even though they are skipped by default
+    /// (nfs, cifs, fuse.sshfs, proc, sysfs, ...); `all` disables the skip list
+    #[arg(long, value_name = "TYPE", value_delimiter = ',')]
+    include_fs: Vec<String>,
+
+    /// Descend into symlinked directories (each directory is scanned once, so loops are safe)
+    #[arg(long, overrides_with = "no_follow_symlinks")]
+    follow_symlinks: bool,
+
+    /// Don't descend into symlinked directories even if the config file sets follow_symlinks
+    #[arg(long, overrides_with = "follow_symlinks")]
+    no_follow_symlinks: bool,
+
+    /// Never delete artifacts under this path (repeatable).
+    /// Projects containing a `.nm-keep` file are protected as well
+    #[arg(long, value_name = "PATH", value_parser = parse_protected_path)]
+    protect: Vec<PathBuf>,
+
+    /// Color scheme for the interactive UI
+    #[arg(long, value_enum)]
+    theme: Option<ThemeName>,
+
+    /// Read settings from this file instead of ~/.config/nm-remover/config.toml
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Apply the [profile.NAME] section of the config file
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Output format for --list
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, requires = "list")]
+    format: OutputFormat,
+
     /// Delete all found node_modules without confirmation (dangerous!)
     #[arg(long)]
     delete_all: bool,
+
+    /// Move folders to the trash instead of deleting them permanently
+    #[arg(long)]
+    trash: bool,
+
+    /// Delete permanently even if the config file selects the trash
+    #[arg(long, conflicts_with = "trash")]
+    permanent: bool,
+
+    /// Show what would be deleted without touching the disk
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Also delete node_modules that are committed to git (this shows up as a huge diff).
+    /// Not implied by --force; pass both to override every guard
+    #[arg(long)]
+    force_tracked: bool,
+
+    /// Also delete node_modules of projects that running processes are using.
+    /// Folders tracked by git still need --force-tracked
+    #[arg(long)]
+    force: bool,
+
+    /// Measure every folder and leave the scan cache untouched
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Re-measure every folder and replace what is cached. A cached size is reused
+    /// while no directory inside the folder changed, so files rewritten in place need this
+    #[arg(long, conflicts_with = "no_cache")]
+    refresh: bool,
+
+    /// Keep the interactive list live: pick up new, removed and re-sized folders
+    /// as they change on disk (toggle with `w`)
+    #[arg(long, conflicts_with_all = ["list", "delete_all"])]
+    watch: bool,
+
+    /// Only include node_modules whose project has been idle for this long (e.g. 30d, 12h, 2w)
+    #[arg(long, value_name = "AGE", value_parser = parse_age)]
+    older_than: Option<Duration>,
+
+    /// Only include projects whose last git commit is at least this old
+    #[arg(long, value_name = "AGE", value_parser = parse_age)]
+    commit_older_than: Option<Duration>,
+
+    /// Only include node_modules at least this large (e.g. 200MB)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    min_size: Option<u64>,
+
+    /// Only include node_modules at most this large (e.g. 2GB)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_size: Option<u64>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show past scans and deletions: space freed per week and folders that keep
+    /// coming back. To scan a directory named `history`, pass it as `./history`
+    History {
+        /// How many recent weeks to list
+        #[arg(long, value_name = "N", default_value_t = 12)]
+        weeks: usize,
+
+        /// How many repeat offenders to list
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        top: usize,
+    },
+}
+
+fn expand_path(input: &str) -> PathBuf {
+    PathBuf::from(shellexpand::tilde(input.trim()).to_string())
+}
+
+/// Split the welcome screen input into roots; paths are separated by commas or newlines
+fn parse_roots(input: &str) -> Vec<PathBuf> {
+    input
+        .split([',', '\n'])
+        .filter(|part| !part.trim().is_empty())
+        .map(expand_path)
+        .collect()
+}
+
+fn display_roots(roots: &[PathBuf]) -> String {
+    roots
+        .iter()
+        .map(|root| root.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Resolve a `--protect` path so it can be compared with scanned paths
+fn parse_protected_path(input: &str) -> Result<PathBuf, String> {
+    let path = expand_path(input);
+    path.canonicalize()
+        .map_err(|e| format!("cannot protect '{}': {}", input, e))
+}
+
+/// Settings carried from the command line into the interactive UI
+struct TuiOptions {
+    scan_options: ScanOptions,
+    delete_mode: DeleteMode,
+    dry_run: bool,
+    force_tracked: bool,
+    force: bool,
+    cache_mode: CacheMode,
+    watch: bool,
+    entry_filter: EntryFilter,
+    sort_key: SortKey,
+    sort_reverse: bool,
+    theme: ThemeName,
+}
+
+/// A list from the command line, or from the config file when none was given
+fn cli_or_config(cli: &[String], config: &Option<Vec<String>>) -> Vec<String> {
+    if cli.is_empty() {
+        config.clone().unwrap_or_default()
+    } else {
+        cli.to_vec()
+    }
+}
+
+/// A `--flag` / `--no-flag` pair: whichever was given, else the config file, else off
+fn cli_flag_or_config(on: bool, off: bool, config: Option<bool>) -> bool {
+    if on || off {
+        on
+    } else {
+        config.unwrap_or(false)
+    }
+}
+
+/// Resolve the `protect` paths of the config file. The file may be shared between
+/// machines, so a path that doesn't exist here is skipped with a warning.
+fn config_protected_paths(paths: &[String]) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .filter_map(|path| match parse_protected_path(path) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                eprintln!("Warning: {}; ignoring it", e);
+                None
+            }
+        })
+        .collect()
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    if let Some(Command::History { weeks, top }) = args.command {
+        return print_history(weeks, top);
+    }
+    let settings = Config::load(args.config.as_deref())?.settings(args.profile.as_deref())?;
+
+    let delete_mode = if args.trash {
+        DeleteMode::Trash
+    } else if args.permanent {
+        DeleteMode::Permanent
+    } else {
+        settings.delete_mode.unwrap_or_default()
+    };
+    let cache_mode = if args.no_cache {
+        CacheMode::Off
+    } else if args.refresh {
+        CacheMode::Refresh
+    } else {
+        CacheMode::Use
+    };
+    let entry_filter = EntryFilter {
+        older_than: args.older_than.or(settings.older_than()?),
+        commit_older_than: args.commit_older_than.or(settings.commit_older_than()?),
+        min_size: args.min_size.or(settings.min_size()?),
+        max_size: args.max_size.or(settings.max_size()?),
+    };
+    let kinds = cli_or_config(&args.kind, &settings.kind);
+    let detectors = if kinds.is_empty() {
+        detector::default_detectors()
+    } else {
+        detector::detectors_for(&kinds)
+    };
+    let what = detector::describe(&detectors);
+    let protected_paths = if args.protect.is_empty() {
+        config_protected_paths(settings.protect.as_deref().unwrap_or_default())
+    } else {
+        args.protect.clone()
+    };
+    let scan_options = ScanOptions {
+        detectors,
+        exclude: cli_or_config(&args.exclude, &settings.exclude),
+        protected_paths,
+        one_file_system: cli_flag_or_config(
+            args.one_file_system,
+            args.no_one_file_system,
+            settings.one_file_system,
+        ),
+        follow_symlinks: cli_flag_or_config(
+            args.follow_symlinks,
+            args.no_follow_symlinks,
+            settings.follow_symlinks,
+        ),
+        // Lists and the TUI show it for every entry and --older-than filters on it;
+        // only a plain --delete-all can do without
+        activity: !args.delete_all || entry_filter.older_than.is_some(),
+        skipped_mounts: Arc::new(mounts::skipped_mount_points(
+            &mounts::read_mounts(),
+            &cli_or_config(&args.include_fs, &settings.include_fs),
+        )),
+        ..ScanOptions::default()
+    };
+    let tui_options = TuiOptions {
+        scan_options: scan_options.clone(),
+        delete_mode,
+        dry_run: args.dry_run,
+        force_tracked: args.force_tracked,
+        force: args.force,
+        cache_mode,
+        watch: args.watch,
+        entry_filter: entry_filter.clone(),
+        sort_key: args.sort,
+        sort_reverse: args.reverse,
+        theme: args.theme.or(settings.theme).unwrap_or_default(),
+    };
 
-    // If path is provided, run in direct mode
-    if let Some(path) = args.path {
-        let path = path.canonicalize().unwrap_or(path.clone());
+    // Without paths on the command line, fall back to the configured roots
+    let roots = if args.paths.is_empty() {
+        settings
+            .roots
+            .iter()
+            .flatten()
+            .map(|root| expand_path(root))
+            .collect()
+    } else {
+        args.paths.clone()
+    };
+
+    // If paths are provided, run in direct mode
+    if !roots.is_empty() {
+        for path in &roots {
+            if !path.exists() {
+                eprintln!("Error: Path '{}' does not exist", path.display());
+                std::process::exit(1);
+            }
 
-        if !path.exists() {
-            eprintln!("Error: Path '{}' does not exist", path.display());
-            std::process::exit(1);
+            if !path.is_dir() {
+                eprintln!("Error: Path '{}' is not a directory", path.display());
+                std::process::exit(1);
+            }
+        }
+        let roots = normalize_roots(&roots);
+
+        // Interactive TUI mode - entries stream into the list while scanning
+        if !args.list && !args.delete_all {
+            return run_tui(roots, tui_options);
         }
 
-        if !path.is_dir() {
-            eprintln!("Error: Path '{}' is not a directory", path.display());
-            std::process::exit(1);
+        // Machine-readable list output - no banners, only records on stdout
+        if args.list && args.format != OutputFormat::Text {
+            return print_list(
+                &roots,
+                &scan_options,
+                cache_mode,
+                args.format,
+                &entry_filter,
+                args.sort,
+                args.reverse,
+            );
         }
 
-        println!("Scanning for node_modules in: {}", path.display());
+        println!("Scanning for {} in: {}", what, display_roots(&roots));
         println!("This may take a while...\n");
 
-        let entries = scan_for_node_modules(&path, None)?;
+        let (entries, cache_stats) = scan_cached(&roots, &scan_options, cache_mode)?;
+        let found = entries.len();
+        let mut entries = entry_filter.apply(entries);
+        sort_entries(&mut entries, args.sort, args.reverse);
 
         if entries.is_empty() {
-            println!("No node_modules folders found.");
+            if found > 0 {
+                println!("No {} folders match the filters ({} found).", what, found);
+            } else {
+                println!("No {} folders found.", what);
+            }
             return Ok(());
         }
 
         // List mode - just print and exit
         if args.list {
-            println!("Found {} node_modules folders:\n", entries.len());
-            let total_size: u64 = entries.iter().map(|e| e.size).sum();
+            match cache_stats {
+                Some(stats) => println!(
+                    "Found {} {} folders ({}):\n",
+                    entries.len(),
+                    what,
+                    stats.describe()
+                ),
+                None => println!("Found {} {} folders:\n", entries.len(), what),
+            }
 
+            let mut writer = ListWriter::new(OutputFormat::Text, stdout())?;
             for entry in &entries {
-                println!(
-                    "  {} [{}] ({})",
-                    entry.path.display(),
-                    entry.size_human(),
-                    entry.last_modified_human()
-                );
+                writer.write_entry(entry)?;
             }
-
-            println!("\nTotal size: {}", bytesize::ByteSize::b(total_size));
+            writer.finish()?;
             return Ok(());
         }
 
         // Delete all mode - dangerous!
         if args.delete_all {
-            println!("Deleting all {} node_modules folders...", entries.len());
-            let total_size: u64 = entries.iter().map(|e| e.size).sum();
+            let (protected, entries): (Vec<_>, Vec<_>) =
+                entries.into_iter().partition(|e| e.protected);
+            for entry in &protected {
+                println!("Skipping protected {}", entry.path.display());
+            }
+            let (tracked, entries): (Vec<_>, Vec<_>) = entries
+                .into_iter()
+                .partition(|e| e.is_tracked() && !args.force_tracked);
+            for entry in &tracked {
+                println!(
+                    "Skipping {} (tracked by git, use --force-tracked)",
+                    entry.path.display()
+                );
+            }
+            // Check right before deleting: a dev server may have started since the scan
+            let processes = ProcessTable::read();
+            let mut entries = entries;
+            for entry in &mut entries {
+                entry.refresh_in_use(&processes);
+            }
+            let (busy, entries): (Vec<_>, Vec<_>) = entries
+                .into_iter()
+                .partition(|e| !e.in_use.is_empty() && !args.force);
+            for entry in &busy {
+                println!(
+                    "Skipping {} (in use by {}, use --force)",
+                    entry.path.display(),
+                    entry.in_use_summary().unwrap_or_default()
+                );
+            }
+            if entries.is_empty() {
+                println!("Nothing to delete: every match is protected, tracked by git or in use.");
+                return Ok(());
+            }
+            if !protected.is_empty() || !tracked.is_empty() || !busy.is_empty() {
+                println!();
+            }
 
-            for entry in &entries {
-                print!("Deleting {}... ", entry.path.display());
-                match delete_node_modules(&entry.path) {
-                    Ok(_) => println!("✓"),
-                    Err(e) => println!("✗ ({})", e),
+            let (verb, dry_verb) = match delete_mode {
+                DeleteMode::Permanent => ("Deleting", "delete"),
+                DeleteMode::Trash => ("Trashing", "trash"),
+            };
+            // Files hard-linked only between the folders being deleted are freed too
+            let total_size = combined_size(entries.iter());
+            let shared_size: u64 = entries.iter().map(|e| e.shared_size).sum();
+            let shared_note = if shared_size > 0 {
+                format!(
+                    " ({} more is hard-linked from elsewhere and stays on disk)",
+                    bytesize::ByteSize::b(shared_size)
+                )
+            } else {
+                String::new()
+            };
+
+            if args.dry_run {
+                println!(
+                    "Dry run: would process {} {} folders ({}):\n",
+                    entries.len(),
+                    what,
+                    delete_mode.label()
+                );
+                for entry in &entries {
+                    println!(
+                        "  Would {} {} [{}]",
+                        dry_verb,
+                        entry.display_path(),
+                        entry.size_summary()
+                    );
                 }
+                println!(
+                    "\nWould free approximately {}{} (nothing was deleted)",
+                    bytesize::ByteSize::b(total_size),
+                    shared_note
+                );
+                return Ok(());
             }
 
             println!(
-                "\nFreed approximately {}",
-                bytesize::ByteSize::b(total_size)
+                "{} all {} {} folders ({})...",
+                verb,
+                entries.len(),
+                what,
+                delete_mode.label()
             );
-            return Ok(());
-        }
 
-        // Interactive TUI mode with entries
-        run_tui(Some(entries))?;
+            let items: Vec<_> = entries.iter().map(|e| (e.path.clone(), e.size)).collect();
+            // The trash keeps the data on disk, so there is no freed space to measure
+            let space = match delete_mode {
+                DeleteMode::Permanent => SpaceTracker::start(&items),
+                DeleteMode::Trash => SpaceTracker::default(),
+            };
+            let mut deleted = Vec::new();
+            for entry in &entries {
+                print!("{} {}... ", verb, entry.path.display());
+                match delete_node_modules(&entry.path, delete_mode) {
+                    Ok(_) => {
+                        println!("✓");
+                        deleted.push(entry.path.clone());
+                    }
+                    Err(e) => println!("✗ ({})", e),
+                }
+            }
+
+            let recorded = entries
+                .iter()
+                .filter(|e| deleted.contains(&e.path))
+                .map(RecordedEntry::new)
+                .collect();
+            match delete_mode {
+                DeleteMode::Permanent => {
+                    println!(
+                        "\nEstimated {}{}",
+                        bytesize::ByteSize::b(total_size),
+                        shared_note
+                    );
+                    let freed = space.finish(&deleted);
+                    history::record(HistoryEvent::delete(
+                        delete_mode,
+                        recorded,
+                        Some(freed.iter().map(|f| f.actual).sum()),
+                    ));
+                    for freed in freed {
+                        println!("  {}", freed.describe());
+                        if freed.is_short() {
+                            println!(
+                                "  ⚠ Less space came back than expected on {}: files may still be open, snapshotted or hard-linked",
+                                freed.mount_point.display()
+                            );
+                        }
+                    }
+                }
+                DeleteMode::Trash => {
+                    history::record(HistoryEvent::delete(delete_mode, recorded, None));
+                    println!(
+                        "\nMoved approximately {} to the trash (empty the trash to free space)",
+                        bytesize::ByteSize::b(total_size)
+                    );
+                }
+            }
+        }
     } else {
         // No path provided - show welcome screen
-        run_tui(None)?;
+        run_tui(Vec::new(), tui_options)?;
     }
 
     Ok(())
 }
 
-fn run_tui(initial_entries: Option<Vec<scanner::NodeModulesEntry>>) -> Result<()> {
+/// Print the `--history` report
+fn print_history(weeks: usize, top: usize) -> Result<()> {
+    let Some(history) = History::open() else {
+        anyhow::bail!("no data directory for the history (set HOME or XDG_DATA_HOME)");
+    };
+    let events = history.read()?;
+    if events.is_empty() {
+        println!(
+            "No history yet: {} will be created by the next scan.",
+            history.path().display()
+        );
+        return Ok(());
+    }
+    for line in Report::new(&events).lines(weeks, top) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Print scan results in a machine-readable format.
+/// NDJSON records are written as soon as the scanner finds each entry, so they are not sorted.
+fn print_list(
+    roots: &[PathBuf],
+    scan_options: &ScanOptions,
+    cache_mode: CacheMode,
+    format: OutputFormat,
+    entry_filter: &EntryFilter,
+    sort_key: SortKey,
+    sort_reverse: bool,
+) -> Result<()> {
+    if format == OutputFormat::Ndjson {
+        let writer = Arc::new(Mutex::new(ListWriter::new(format, stdout())?));
+        let writer_clone = Arc::clone(&writer);
+        let filter = entry_filter.clone();
+        // A closed pipe (`| head`) ends the scan; nobody reads the rest
+        let failed: Arc<Mutex<Option<anyhow::Error>>> = Arc::new(Mutex::new(None));
+        let failed_clone = Arc::clone(&failed);
+        let cancel = scan_options.cancel.clone();
+
+        let callback: EntryCallback = Arc::new(Mutex::new(move |entry: &NodeModulesEntry| {
+            if !filter.matches(entry) || cancel.is_cancelled() {
+                return;
+            }
+            let Ok(mut w) = writer_clone.lock() else {
+                return;
+            };
+            if let Err(e) = w.write_entry(entry) {
+                if let Ok(mut failed) = failed_clone.lock() {
+                    failed.get_or_insert(e);
+                }
+                cancel.cancel();
+            }
+        }));
+
+        let options = ScanOptions {
+            entry_callback: Some(callback),
+            ..scan_options.clone()
+        };
+        scan_cached(roots, &options, cache_mode)?;
+        drop(options);
+
+        if let Some(error) = failed.lock().ok().and_then(|mut failed| failed.take()) {
+            return Err(error);
+        }
+        let writer = Arc::try_unwrap(writer)
+            .map_err(|_| anyhow::anyhow!("scan still holds the output writer"))?
+            .into_inner()
+            .map_err(|_| anyhow::anyhow!("output writer lock poisoned"))?;
+        return writer.finish();
+    }
+
+    let (entries, _) = scan_cached(roots, scan_options, cache_mode)?;
+    let mut entries = entry_filter.apply(entries);
+    sort_entries(&mut entries, sort_key, sort_reverse);
+    let mut writer = ListWriter::new(format, stdout())?;
+    for entry in &entries {
+        writer.write_entry(entry)?;
+    }
+    writer.finish()
+}
+
+/// Scan `roots` with the on-disk cache, then save it and record the scan in the
+/// history unless it was cancelled. Returns how many sizes came from the cache
+/// when it is enabled.
+fn scan_cached(
+    roots: &[PathBuf],
+    scan_options: &ScanOptions,
+    cache_mode: CacheMode,
+) -> Result<(Vec<NodeModulesEntry>, Option<CacheStats>)> {
+    let cache = ScanCache::open(cache_mode).map(Arc::new);
+    let options = ScanOptions {
+        cache: cache.clone(),
+        ..scan_options.clone()
+    };
+    let entries = scan_roots(roots, &options)?;
+    if !options.cancel.is_cancelled() {
+        history::record(HistoryEvent::scan(roots, &entries));
+    }
+
+    let stats = cache.map(|cache| {
+        if !options.cancel.is_cancelled() {
+            // The cache only speeds up the next scan; failing to write it isn't an error
+            let _ = cache.save(&normalize_roots(roots));
+        }
+        cache.stats()
+    });
+    Ok((entries, stats))
+}
+
+/// Start scanning `roots` in a background thread.
+/// Entries are sent through `entry_tx` as soon as they are found.
+fn start_scan(
+    roots: Vec<PathBuf>,
+    current_path: &Arc<Mutex<String>>,
+    entry_tx: mpsc::Sender<NodeModulesEntry>,
+    cancel: CancelToken,
+    scan_options: &ScanOptions,
+    cache_mode: CacheMode,
+) -> thread::JoinHandle<Result<Option<CacheStats>>> {
+    if let Ok(mut cp) = current_path.lock() {
+        cp.clear();
+    }
+    let current_path_clone = Arc::clone(current_path);
+    let template = scan_options.clone();
+
+    thread::spawn(move || {
+        let progress: ProgressCallback = Arc::new(Mutex::new(move |path: &str| {
+            if let Ok(mut cp) = current_path_clone.lock() {
+                *cp = path.to_string();
+            }
+        }));
+
+        let on_entry: EntryCallback = Arc::new(Mutex::new(move |entry: &NodeModulesEntry| {
+            let _ = entry_tx.send(entry.clone());
+        }));
+
+        let options = ScanOptions {
+            progress_callback: Some(progress),
+            entry_callback: Some(on_entry),
+            cancel,
+            ..template
+        };
+        scan_cached(&roots, &options, cache_mode).map(|(_, stats)| stats)
+    })
+}
+
+/// Add the folders a deletion job removed to the history. `queued` is what the
+/// job started with; the list itself may have lost rows to watch mode since.
+fn record_deletion(mode: DeleteMode, queued: &[RecordedEntry], summary: &DeleteSummary) {
+    let deleted: Vec<RecordedEntry> = queued
+        .iter()
+        .filter(|e| {
+            summary
+                .deleted_paths
+                .iter()
+                .any(|p| p.to_string_lossy() == e.path)
+        })
+        .cloned()
+        .collect();
+    if deleted.is_empty() {
+        return;
+    }
+    let freed = match mode {
+        DeleteMode::Permanent => Some(summary.freed.iter().map(|f| f.actual).sum()),
+        DeleteMode::Trash => None,
+    };
+    history::record(HistoryEvent::delete(mode, deleted, freed));
+}
+
+/// Drop deleted entries from the list, record them in the history and report
+/// the outcome in the status bar
+fn finish_delete(app: &mut App, summary: DeleteSummary, queued: &[RecordedEntry]) {
+    let deleted_count = summary.deleted_paths.len();
+    app.deleting = false;
+    app.delete_cancelling = false;
+    record_deletion(app.delete_mode, queued, &summary);
+    app.remove_deleted(&summary.deleted_paths);
+
+    if summary.skipped > 0 {
+        app.message = Some(format!(
+            "Cancelled: deleted {} folders, {} skipped, {} errors",
+            deleted_count, summary.skipped, summary.errors
+        ));
+    } else if summary.errors > 0 {
+        app.message = Some(format!(
+            "Deleted {} folders, {} errors",
+            deleted_count, summary.errors
+        ));
+    } else if app.delete_mode == DeleteMode::Trash {
+        app.message = Some(format!(
+            "Successfully moved {} folders to trash",
+            deleted_count
+        ));
+    } else {
+        app.message = Some(format!("Successfully deleted {} folders", deleted_count));
+    }
+
+    // One line per filesystem, like the --delete-all report
+    if let Some(message) = app.message.as_mut() {
+        for freed in &summary.freed {
+            message.push('\n');
+            message.push_str(&freed.describe());
+            if freed.is_short() {
+                message.push_str(" ⚠ files may still be open, snapshotted or hard-linked");
+            }
+        }
+    }
+}
+
+fn run_tui(initial_roots: Vec<PathBuf>, options: TuiOptions) -> Result<()> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    // Pasting a list of paths into the welcome screen mustn't submit it line by line
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
 
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     let mut app = App::new();
-
-    // If we have initial entries, go directly to list mode
-    if let Some(entries) = initial_entries {
-        app.set_entries(entries);
-        app.mode = AppMode::List;
-    }
+    app.delete_mode = options.delete_mode;
+    app.dry_run = options.dry_run;
+    app.force_tracked = options.force_tracked;
+    app.force = options.force;
+    app.watch = options.watch;
+    app.sort_key = options.sort_key;
+    app.sort_reverse = options.sort_reverse;
+    app.item_label = detector::describe(&options.scan_options.detectors);
+    app.theme = options.theme.palette();
+    let entry_filter = options.entry_filter.clone();
 
     // Shared state for async scanning
     let current_path: Arc<Mutex<String>> = Arc::new(Mutex::new(String::new()));
-    let scan_result: Arc<Mutex<Option<Result<Vec<NodeModulesEntry>>>>> = Arc::new(Mutex::new(None));
-    let mut scan_handle: Option<thread::JoinHandle<()>> = None;
+    let (entry_tx, entry_rx) = mpsc::channel::<NodeModulesEntry>();
+    let mut scan_handle: Option<thread::JoinHandle<Result<Option<CacheStats>>>> = None;
+    let mut scan_cancel = CancelToken::new();
+    let mut delete_job: Option<DeleteJob> = None;
+    // What the running deletion job was given, for the history
+    let mut delete_queued: Vec<RecordedEntry> = Vec::new();
+    let mut found_count = 0;
+    // Every artifact under the scanned roots, including those the filters hide,
+    // so starting watch mode doesn't have to scan them again
+    let mut found_paths: HashSet<PathBuf> = HashSet::new();
+    let mut watcher: Option<LiveWatcher> = None;
+    let mut scanned_roots = initial_roots.clone();
+
+    // If we have initial roots, start scanning and show the list right away
+    if !initial_roots.is_empty() {
+        app.input_path = display_roots(&initial_roots);
+        app.cursor_position = app.input_path.len();
+        app.scan_path = app.input_path.clone();
+        app.scanning = true;
+        app.mode = AppMode::List;
+        scan_handle = Some(start_scan(
+            initial_roots,
+            &current_path,
+            entry_tx.clone(),
+            scan_cancel.clone(),
+            &options.scan_options,
+            options.cache_mode,
+        ));
+    }
 
     // Main loop
     loop {
-        match app.mode {
-            AppMode::Welcome => {
-                // Update current path from scanning thread
-                if app.scanning {
-                    if let Ok(path) = current_path.lock() {
-                        app.scanning_current_path = path.clone();
-                    }
+        // Abort the running scan and go back to the welcome screen
+        if app.cancel_scan {
+            scan_cancel.cancel();
+            if let Some(handle) = scan_handle.take() {
+                let _ = handle.join();
+            }
+            // Discard entries that were sent before the scan stopped
+            entry_rx.try_iter().for_each(drop);
 
-                    // Check if scan completed
-                    if let Ok(mut result) = scan_result.lock() {
-                        if let Some(scan_res) = result.take() {
-                            // Wait for thread to finish
-                            if let Some(handle) = scan_handle.take() {
-                                let _ = handle.join();
-                            }
-
-                            match scan_res {
-                                Ok(entries) => {
-                                    if entries.is_empty() {
-                                        app.message =
-                                            Some("No node_modules folders found.".to_string());
-                                    } else {
-                                        app.set_entries(entries);
-                                        app.mode = AppMode::List;
-                                    }
-                                }
-                                Err(e) => {
-                                    app.message = Some(format!("Error scanning: {}", e));
-                                }
-                            }
-                            app.scanning = false;
-                            app.scanning_current_path.clear();
-                        }
-                    }
+            app.cancel_scan = false;
+            app.scanning = false;
+            app.scanning_current_path.clear();
+            app.set_entries(Vec::new());
+            app.mode = AppMode::Welcome;
+            app.message = Some("Scan cancelled.".to_string());
+        }
+
+        if app.scanning {
+            // Update current path from scanning thread
+            if let Ok(path) = current_path.lock() {
+                app.scanning_current_path = path.clone();
+            }
+
+            // Check completion before draining so no entry sent before the end is missed
+            let finished = scan_handle
+                .as_ref()
+                .map(|handle| handle.is_finished())
+                .unwrap_or(true);
+
+            // Move entries found so far into the list
+            for entry in entry_rx.try_iter() {
+                found_count += 1;
+                found_paths.insert(entry.path.clone());
+                if entry_filter.matches(&entry) {
+                    app.add_entry(entry);
+                }
+            }
+
+            if finished {
+                let result = match scan_handle.take().map(|handle| handle.join()) {
+                    Some(Ok(result)) => result,
+                    Some(Err(_)) => Err(anyhow::anyhow!("scanner thread panicked")),
+                    None => Ok(None),
+                };
+                app.cache_stats = result.as_ref().ok().copied().flatten();
+                scanner::classify_shared(&mut app.entries);
+
+                if let Err(e) = result {
+                    app.message = Some(format!("Error scanning: {}", e));
+                } else if app.entries.is_empty() && found_count > 0 {
+                    app.message = Some(format!(
+                        "No {} folders match the filters ({} found).",
+                        app.item_label, found_count
+                    ));
+                } else if app.entries.is_empty() {
+                    app.message = Some(format!("No {} folders found.", app.item_label));
+                }
+
+                // Nothing to show - go back to the welcome screen
+                if app.entries.is_empty() {
+                    app.mode = AppMode::Welcome;
                 }
 
+                app.scanning = false;
+                app.scanning_current_path.clear();
+            }
+        }
+
+        // Watch mode follows changes on disk once the list is complete
+        let want_watch = app.watch && !app.scanning && app.mode == AppMode::List;
+        if want_watch && watcher.is_none() {
+            // A change is what triggers a re-scan, so always measure it
+            let watch_options = ScanOptions {
+                cache: None,
+                ..options.scan_options.clone()
+            };
+            match LiveWatcher::start(scanned_roots.clone(), watch_options, found_paths.clone()) {
+                Ok(started) => watcher = Some(started),
+                Err(e) => {
+                    app.watch = false;
+                    app.message = Some(format!("Can't watch for changes: {}", e));
+                }
+            }
+        } else if !want_watch {
+            if let Some(watcher) = watcher.take() {
+                watcher.stop();
+            }
+        }
+        if let Some(watcher) = &watcher {
+            if let Some(error) = watcher.take_error() {
+                app.message = Some(format!(
+                    "Watch mode: {}; some changes will be missed",
+                    error
+                ));
+            }
+            for update in watcher.try_updates() {
+                found_paths.retain(|path| !path.starts_with(&update.dir));
+                found_paths.extend(update.entries.iter().map(|e| e.path.clone()));
+                let entries = update
+                    .entries
+                    .into_iter()
+                    .filter(|e| entry_filter.matches(e))
+                    .collect();
+                app.apply_watch_update(&update.dir, entries);
+            }
+        }
+        app.expire_flashes();
+
+        match app.mode {
+            AppMode::Welcome => {
                 terminal.draw(|f| draw_welcome(f, &mut app))?;
 
-                // Use poll to avoid blocking during scanning
-                if app.scanning {
-                    // Non-blocking: poll for escape key
-                    if event::poll(Duration::from_millis(50))? {
-                        if let Event::Key(key) = event::read()? {
-                            if key.code == KeyCode::Esc {
-                                app.should_quit = true;
-                            }
-                        }
-                    }
-                } else if let Some(path) = handle_welcome_input(&mut app)? {
-                    // User submitted a path - start scanning in background
-                    let scan_path = PathBuf::from(shellexpand::tilde(&path).to_string());
+                if let Some(input) = handle_welcome_input(&mut app)? {
+                    // User submitted one or more paths - start scanning in background
+                    let roots = parse_roots(&input);
 
-                    if scan_path.exists() && scan_path.is_dir() {
+                    if !roots.is_empty() && roots.iter().all(|p| p.is_dir()) {
+                        let roots = normalize_roots(&roots);
+                        if let Some(watcher) = watcher.take() {
+                            watcher.stop();
+                        }
+                        scanned_roots = roots.clone();
+                        app.set_entries(Vec::new());
                         app.scanning = true;
-                        app.scan_path = path.clone();
+                        app.scan_path = display_roots(&roots);
                         app.scanning_current_path.clear();
+                        app.mode = AppMode::List;
+                        found_count = 0;
+                        found_paths.clear();
 
-                        // Clone Arc references for the thread
-                        let current_path_clone = Arc::clone(&current_path);
-                        let scan_result_clone = Arc::clone(&scan_result);
-
-                        // Start scanning in background thread
-                        scan_handle = Some(thread::spawn(move || {
-                            let callback: ProgressCallback =
-                                Arc::new(Mutex::new(move |path: &str| {
-                                    if let Ok(mut cp) = current_path_clone.lock() {
-                                        *cp = path.to_string();
-                                    }
-                                }));
-
-                            let result = scan_for_node_modules(&scan_path, Some(callback));
-
-                            if let Ok(mut res) = scan_result_clone.lock() {
-                                *res = Some(result);
-                            }
-                        }));
+                        scan_cancel = CancelToken::new();
+                        scan_handle = Some(start_scan(
+                            roots,
+                            &current_path,
+                            entry_tx.clone(),
+                            scan_cancel.clone(),
+                            &options.scan_options,
+                            options.cache_mode,
+                        ));
                     } else {
-                        app.message =
-                            Some("Invalid path. Please enter a valid directory.".to_string());
+                        app.message = Some(
+                            "Invalid path. Please enter existing directories, separated by commas or newlines."
+                                .to_string(),
+                        );
                     }
                 }
             }
@@ -224,50 +1008,53 @@ fn run_tui(initial_entries: Option<Vec<scanner::NodeModulesEntry>>) -> Result<()
 
                 let should_delete = handle_input(&mut app)?;
 
-                if should_delete && !app.selected.is_empty() {
+                if should_delete && !app.selected.is_empty() && app.dry_run {
+                    // Dry run - report what the delete would do and keep the list intact
+                    app.message = Some(app.dry_run_message());
+                    app.deselect_all();
+                } else if should_delete
+                    && !app.selected.is_empty()
+                    && delete_job.is_none()
+                    && !app.deselect_in_use(&ProcessTable::read())
+                {
                     // Collect paths first to avoid borrow issues
-                    let entries_to_delete: Vec<(usize, PathBuf)> = app
+                    let entries_to_delete: Vec<DeleteItem> = app
                         .selected
                         .iter()
-                        .filter_map(|&i| app.entries.get(i).map(|e| (i, e.path.clone())))
+                        .filter_map(|&i| app.entries.get(i).map(|e| (e.path.clone(), e.size)))
+                        .collect();
+                    delete_queued = app
+                        .selected
+                        .iter()
+                        .filter_map(|&i| app.entries.get(i).map(RecordedEntry::new))
                         .collect();
-
-                    let total = entries_to_delete.len();
-                    let mut deleted_indices = Vec::new();
-                    let mut deleted_count = 0;
-                    let mut error_count = 0;
 
                     app.deleting = true;
-                    app.delete_progress = (0, total);
-
-                    for (idx, (i, path)) in entries_to_delete.iter().enumerate() {
-                        // Update progress display
-                        app.delete_progress = (idx + 1, total);
-                        app.delete_current_path = path.to_string_lossy().to_string();
-                        terminal.draw(|f| draw(f, &mut app))?;
-
-                        match delete_node_modules(path) {
-                            Ok(_) => {
-                                deleted_count += 1;
-                                deleted_indices.push(*i);
-                            }
-                            Err(_) => {
-                                error_count += 1;
-                            }
-                        }
+                    app.delete_progress = (0, entries_to_delete.len());
+                    delete_job = Some(DeleteJob::start(entries_to_delete, app.delete_mode));
+                }
+
+                // Update progress from the background deletion workers
+                if let Some(job) = delete_job.as_mut() {
+                    if app.cancel_delete {
+                        app.cancel_delete = false;
+                        job.cancel();
                     }
 
-                    app.deleting = false;
-                    app.remove_deleted(&deleted_indices);
+                    let finished = job.is_finished();
+                    app.delete_progress = (job.poll(), job.total);
+                    app.delete_files_removed = job.progress.files_removed.load(Ordering::Relaxed);
+                    app.delete_bytes_removed = job.bytes_removed();
+                    app.delete_bytes_total = job.total_bytes;
+                    app.delete_cancelling = job.is_cancelled();
+                    if let Ok(path) = job.progress.current_path.lock() {
+                        app.delete_current_path = path.clone();
+                    }
 
-                    if error_count > 0 {
-                        app.message = Some(format!(
-                            "Deleted {} folders, {} errors",
-                            deleted_count, error_count
-                        ));
-                    } else {
-                        app.message =
-                            Some(format!("Successfully deleted {} folders", deleted_count));
+                    if finished {
+                        if let Some(job) = delete_job.take() {
+                            finish_delete(&mut app, job.finish(), &delete_queued);
+                        }
                     }
                 }
             }
@@ -278,9 +1065,46 @@ fn run_tui(initial_entries: Option<Vec<scanner::NodeModulesEntry>>) -> Result<()
         }
     }
 
+    // Let folders that are already being removed finish, skip the rest
+    if let Some(job) = delete_job.take() {
+        job.cancel();
+        record_deletion(app.delete_mode, &delete_queued, &job.finish());
+    }
+
+    if let Some(watcher) = watcher.take() {
+        watcher.stop();
+    }
+
+    // Stop a scan that is still running so its threads don't outlive the UI
+    scan_cancel.cancel();
+    if let Some(handle) = scan_handle.take() {
+        let _ = handle.join();
+    }
+
     // Restore terminal
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(
+        terminal.backend_mut(),
+        DisableBracketedPaste,
+        LeaveAlternateScreen
+    )?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_roots_accepts_commas_and_newlines() {
+        assert_eq!(
+            parse_roots("/work, /oss\n/tmp/a\n\n,"),
+            vec![
+                PathBuf::from("/work"),
+                PathBuf::from("/oss"),
+                PathBuf::from("/tmp/a")
+            ]
+        );
+    }
+}
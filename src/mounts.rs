@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Filesystem types the scanner never descends into unless named in `--include-fs`:
+/// network mounts that are slow or may hang, and kernel pseudo filesystems
+pub const DEFAULT_SKIP_FS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "fuse.sshfs",
+    "fuse.rclone",
+    "proc",
+    "sysfs",
+    "devpts",
+    "devtmpfs",
+    "cgroup",
+    "cgroup2",
+    "debugfs",
+    "tracefs",
+    "securityfs",
+    "configfs",
+    "autofs",
+];
+
+/// One line of `/proc/self/mountinfo`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+}
+
+/// Parse the contents of `/proc/self/mountinfo`; malformed lines are skipped
+pub fn parse_mountinfo(text: &str) -> Vec<Mount> {
+    text.lines()
+        .filter_map(|line| {
+            let (left, right) = line.split_once(" - ")?;
+            let mount_point = left.split(' ').nth(4)?;
+            let fs_type = right.split(' ').next()?;
+            Some(Mount {
+                mount_point: PathBuf::from(unescape(mount_point)),
+                fs_type: fs_type.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Mount points are written with spaces, tabs and backslashes as octal escapes (`\040`)
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+            let digits = std::str::from_utf8(&bytes[i + 1..i + 4]).ok();
+            if let Some(code) = digits.and_then(|d| u8::from_str_radix(d, 8).ok()) {
+                out.push(code);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Mount points whose filesystem type is in the skip list and not in `include_fs`.
+/// `include_fs` may contain `all` to turn the skip list off.
+pub fn skipped_mount_points(mounts: &[Mount], include_fs: &[String]) -> HashSet<PathBuf> {
+    if include_fs.iter().any(|fs| fs == "all") {
+        return HashSet::new();
+    }
+    mounts
+        .iter()
+        .filter(|m| DEFAULT_SKIP_FS.contains(&m.fs_type.as_str()))
+        .filter(|m| !include_fs.iter().any(|fs| fs == &m.fs_type))
+        .map(|m| m.mount_point.clone())
+        .collect()
+}
+
+/// Mounts of the current process; empty where `/proc` is not available
+pub fn read_mounts() -> Vec<Mount> {
+    std::fs::read_to_string("/proc/self/mountinfo")
+        .map(|text| parse_mountinfo(&text))
+        .unwrap_or_default()
+}
+
+/// Device id of the filesystem holding `path`
+#[cfg(unix)]
+pub fn device_id(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|m| m.dev())
+}
+
+#[cfg(not(unix))]
+pub fn device_id(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTINFO: &str = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+23 22 0:21 / /proc rw,nosuid shared:12 - proc proc rw
+41 22 0:40 / /mnt/nas rw,relatime shared:30 - nfs4 nas:/export rw,vers=4.2
+42 22 0:41 / /home/me/remote\\040box rw shared:31 - fuse.sshfs me@box: rw
+43 22 0:42 / /home/me/code rw shared:32 - btrfs /dev/sda2 rw
+";
+
+    #[test]
+    fn test_parse_mountinfo() {
+        let mounts = parse_mountinfo(MOUNTINFO);
+
+        assert_eq!(mounts.len(), 5);
+        assert_eq!(mounts[2].fs_type, "nfs4");
+        assert_eq!(mounts[3].mount_point, PathBuf::from("/home/me/remote box"));
+    }
+
+    #[test]
+    fn test_skip_list_and_include_fs() {
+        let mounts = parse_mountinfo(MOUNTINFO);
+
+        let skipped = skipped_mount_points(&mounts, &[]);
+        assert!(skipped.contains(Path::new("/proc")));
+        assert!(skipped.contains(Path::new("/mnt/nas")));
+        assert!(skipped.contains(Path::new("/home/me/remote box")));
+        assert!(!skipped.contains(Path::new("/home/me/code")));
+
+        let skipped = skipped_mount_points(&mounts, &["nfs4".to_string()]);
+        assert!(!skipped.contains(Path::new("/mnt/nas")));
+        assert!(skipped_mount_points(&mounts, &["all".to_string()]).is_empty());
+    }
+}
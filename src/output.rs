@@ -0,0 +1,458 @@
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::scanner::NodeModulesEntry;
+
+/// Output format for `--list`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// A single JSON document with all entries and totals
+    Json,
+    /// One JSON object per line, streamed while scanning; leaves out
+    /// `shared_with_entries`, which is only known at the end
+    Ndjson,
+    /// Comma-separated values with a header row
+    Csv,
+    /// Tab-separated values with a header row
+    Tsv,
+}
+
+#[derive(Debug, Serialize)]
+struct EntryRecord {
+    #[serde(rename = "type")]
+    record_type: &'static str,
+    path: String,
+    root: String,
+    kind: &'static str,
+    size: u64,
+    apparent_size: u64,
+    shared_size: u64,
+    /// Left out of streamed NDJSON: it is only known once the scan has finished
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shared_with_entries: Option<u64>,
+    last_modified: Option<String>,
+    last_modified_epoch: Option<u64>,
+    last_activity: Option<String>,
+    last_activity_epoch: Option<u64>,
+    protected: bool,
+    link_target: Option<String>,
+    name: Option<String>,
+    version: Option<String>,
+    private: bool,
+    workspaces: Vec<String>,
+    lockfile: Option<&'static str>,
+    orphaned: bool,
+    git_repo: bool,
+    last_commit: Option<String>,
+    last_commit_epoch: Option<u64>,
+    git_dirty: bool,
+    git_tracked: bool,
+    in_use: Vec<String>,
+}
+
+impl EntryRecord {
+    fn new(entry: &NodeModulesEntry) -> Self {
+        let project = entry.project.as_ref();
+        let git = entry.git.as_ref();
+        let last_commit = git.and_then(|g| g.last_commit);
+
+        Self {
+            record_type: "entry",
+            path: entry.path.to_string_lossy().to_string(),
+            root: entry.root.to_string_lossy().to_string(),
+            kind: entry.kind,
+            size: entry.size,
+            apparent_size: entry.apparent_size,
+            shared_size: entry.shared_size,
+            shared_with_entries: Some(entry.shared_with_entries),
+            last_modified: entry.last_modified.map(rfc3339),
+            last_modified_epoch: entry.last_modified.and_then(epoch),
+            last_activity: entry.last_activity.map(rfc3339),
+            last_activity_epoch: entry.last_activity.and_then(epoch),
+            protected: entry.protected,
+            link_target: entry
+                .link_target
+                .as_ref()
+                .map(|t| t.to_string_lossy().to_string()),
+            name: project.and_then(|p| p.name.clone()),
+            version: project.and_then(|p| p.version.clone()),
+            private: project.is_some_and(|p| p.private),
+            workspaces: project.map(|p| p.workspaces.clone()).unwrap_or_default(),
+            lockfile: project.and_then(|p| p.lockfile).map(|l| l.label()),
+            orphaned: entry.orphaned,
+            git_repo: git.is_some(),
+            last_commit: last_commit.map(rfc3339),
+            last_commit_epoch: last_commit.and_then(epoch),
+            git_dirty: git.is_some_and(|g| g.dirty),
+            git_tracked: git.is_some_and(|g| g.tracked),
+            in_use: entry.in_use.iter().map(|p| p.label()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TotalRecord {
+    #[serde(rename = "type")]
+    record_type: &'static str,
+    count: usize,
+    size: u64,
+    shared_size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shared_with_entries: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonDocument {
+    entries: Vec<EntryRecord>,
+    total: TotalRecord,
+}
+
+/// CSV and TSV header; named like the JSON keys so scripts can switch formats
+const COLUMNS: [&str; 26] = [
+    "type",
+    "path",
+    "root",
+    "kind",
+    "size",
+    "apparent_size",
+    "shared_size",
+    "shared_with_entries",
+    "last_modified",
+    "last_modified_epoch",
+    "last_activity",
+    "last_activity_epoch",
+    "protected",
+    "link_target",
+    "name",
+    "version",
+    "private",
+    "workspaces",
+    "lockfile",
+    "orphaned",
+    "git_repo",
+    "last_commit",
+    "last_commit_epoch",
+    "git_dirty",
+    "git_tracked",
+    "in_use",
+];
+
+/// Writes list output in the selected format.
+/// Entries are written as they are added, except for `Json` which is
+/// emitted as a single document by `finish`.
+pub struct ListWriter<W: Write> {
+    format: OutputFormat,
+    out: W,
+    records: Vec<EntryRecord>,
+    count: usize,
+    total_size: u64,
+    total_shared: u64,
+    total_shared_with_entries: u64,
+}
+
+impl<W: Write> ListWriter<W> {
+    pub fn new(format: OutputFormat, mut out: W) -> Result<Self> {
+        if let Some(sep) = separator(format) {
+            writeln!(out, "{}", COLUMNS.join(&sep.to_string()))?;
+        }
+
+        Ok(Self {
+            format,
+            out,
+            records: Vec::new(),
+            count: 0,
+            total_size: 0,
+            total_shared: 0,
+            total_shared_with_entries: 0,
+        })
+    }
+
+    pub fn write_entry(&mut self, entry: &NodeModulesEntry) -> Result<()> {
+        self.count += 1;
+        self.total_size += entry.size;
+        self.total_shared += entry.shared_size;
+        self.total_shared_with_entries += entry.shared_with_entries;
+        let mut record = EntryRecord::new(entry);
+        if self.format == OutputFormat::Ndjson {
+            record.shared_with_entries = None;
+        }
+
+        match self.format {
+            OutputFormat::Json => self.records.push(record),
+            OutputFormat::Ndjson => {
+                serde_json::to_writer(&mut self.out, &record)?;
+                writeln!(self.out)?;
+                self.out.flush()?;
+            }
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                let fields = [
+                    record.record_type.to_string(),
+                    record.path,
+                    record.root,
+                    record.kind.to_string(),
+                    record.size.to_string(),
+                    record.apparent_size.to_string(),
+                    record.shared_size.to_string(),
+                    record
+                        .shared_with_entries
+                        .map(|s| s.to_string())
+                        .unwrap_or_default(),
+                    record.last_modified.unwrap_or_default(),
+                    record
+                        .last_modified_epoch
+                        .map(|e| e.to_string())
+                        .unwrap_or_default(),
+                    record.last_activity.unwrap_or_default(),
+                    record
+                        .last_activity_epoch
+                        .map(|e| e.to_string())
+                        .unwrap_or_default(),
+                    record.protected.to_string(),
+                    record.link_target.unwrap_or_default(),
+                    record.name.unwrap_or_default(),
+                    record.version.unwrap_or_default(),
+                    record.private.to_string(),
+                    record.workspaces.join(";"),
+                    record.lockfile.unwrap_or_default().to_string(),
+                    record.orphaned.to_string(),
+                    record.git_repo.to_string(),
+                    record.last_commit.unwrap_or_default(),
+                    record
+                        .last_commit_epoch
+                        .map(|e| e.to_string())
+                        .unwrap_or_default(),
+                    record.git_dirty.to_string(),
+                    record.git_tracked.to_string(),
+                    record.in_use.join(";"),
+                ];
+                self.write_row(&fields)?;
+            }
+            OutputFormat::Text => {
+                writeln!(
+                    self.out,
+                    "  {:<8} {} [{}] ({}, active {}){}{}{}{}",
+                    entry.kind,
+                    entry.display_path(),
+                    entry.size_summary(),
+                    entry.last_modified_human(),
+                    entry.last_activity_human(),
+                    project_summary(entry),
+                    git_summary(entry),
+                    entry
+                        .in_use_summary()
+                        .map(|users| format!(" [in use: {}]", users))
+                        .unwrap_or_default(),
+                    if entry.protected { " [protected]" } else { "" }
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the totals record and flush the output
+    pub fn finish(mut self) -> Result<()> {
+        let total = TotalRecord {
+            record_type: "total",
+            count: self.count,
+            size: self.total_size,
+            shared_size: self.total_shared,
+            shared_with_entries: (self.format != OutputFormat::Ndjson)
+                .then_some(self.total_shared_with_entries),
+        };
+
+        match self.format {
+            OutputFormat::Json => {
+                let document = JsonDocument {
+                    entries: std::mem::take(&mut self.records),
+                    total,
+                };
+                serde_json::to_writer_pretty(&mut self.out, &document)?;
+                writeln!(self.out)?;
+            }
+            OutputFormat::Ndjson => {
+                serde_json::to_writer(&mut self.out, &total)?;
+                writeln!(self.out)?;
+            }
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                let mut fields = vec![String::new(); COLUMNS.len()];
+                fields[column("type")] = total.record_type.to_string();
+                fields[column("size")] = total.size.to_string();
+                fields[column("shared_size")] = total.shared_size.to_string();
+                fields[column("shared_with_entries")] =
+                    total.shared_with_entries.unwrap_or(0).to_string();
+                self.write_row(&fields)?;
+            }
+            OutputFormat::Text => {
+                writeln!(
+                    self.out,
+                    "\nTotal size: {}",
+                    bytesize::ByteSize::b(total.size)
+                )?;
+                if total.shared_size > 0 {
+                    writeln!(
+                        self.out,
+                        "Shared with other folders: {} (hard links, not freed by deleting)",
+                        bytesize::ByteSize::b(total.shared_size)
+                    )?;
+                }
+                if let Some(shared) = total.shared_with_entries.filter(|&s| s > 0) {
+                    // Each file is counted once per listed folder that links it
+                    writeln!(
+                        self.out,
+                        "Shared between listed folders: {} (freed once every folder linking them is deleted)",
+                        bytesize::ByteSize::b(shared)
+                    )?;
+                }
+            }
+        }
+
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn write_row(&mut self, fields: &[String]) -> Result<()> {
+        let sep = separator(self.format).unwrap_or(',');
+        let row: Vec<String> = fields.iter().map(|f| escape_field(f, sep)).collect();
+        writeln!(self.out, "{}", row.join(&sep.to_string()))?;
+        Ok(())
+    }
+}
+
+fn rfc3339(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn epoch(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+fn column(name: &str) -> usize {
+    COLUMNS.iter().position(|c| *c == name).unwrap_or(0)
+}
+
+/// ` - name@version (lockfile) [private, ws]` for the text list, or ` [orphaned]`
+fn project_summary(entry: &NodeModulesEntry) -> String {
+    if entry.orphaned {
+        return " [orphaned]".to_string();
+    }
+    let Some(project) = &entry.project else {
+        return String::new();
+    };
+    let mut summary = String::new();
+    if let Some(label) = project.label() {
+        summary.push_str(&format!(" - {}", label));
+    }
+    if let Some(lockfile) = project.lockfile {
+        summary.push_str(&format!(" ({})", lockfile.label()));
+    }
+    summary.push_str(&project.tags());
+    summary
+}
+
+/// ` [commit 3d ago, dirty]`, or ` [tracked by git]` for committed artifacts
+fn git_summary(entry: &NodeModulesEntry) -> String {
+    match &entry.git {
+        Some(git) if git.tracked => " [tracked by git]".to_string(),
+        Some(git) => format!(
+            " [commit {}{}]",
+            entry.last_commit_human(),
+            if git.dirty { ", dirty" } else { "" }
+        ),
+        None => String::new(),
+    }
+}
+
+fn separator(format: OutputFormat) -> Option<char> {
+    match format {
+        OutputFormat::Csv => Some(','),
+        OutputFormat::Tsv => Some('\t'),
+        _ => None,
+    }
+}
+
+/// Quote a field if it contains the separator, quotes or line breaks (RFC 4180)
+fn escape_field(field: &str, sep: char) -> String {
+    if field.contains(sep) || field.contains('"') || field.contains('\n') || field.contains('\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn entry(path: &str, size: u64) -> NodeModulesEntry {
+        NodeModulesEntry {
+            path: PathBuf::from(path),
+            root: PathBuf::from(path).parent().unwrap().to_path_buf(),
+            size,
+            last_modified: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            kind: "node",
+            ..Default::default()
+        }
+    }
+
+    fn render(format: OutputFormat, entries: &[NodeModulesEntry]) -> String {
+        let mut buf = Vec::new();
+        let mut writer = ListWriter::new(format, &mut buf).unwrap();
+        for e in entries {
+            writer.write_entry(e).unwrap();
+        }
+        writer.finish().unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_ndjson_has_entry_and_total_records() {
+        let out = render(OutputFormat::Ndjson, &[entry("/a/node_modules", 10)]);
+        let lines: Vec<serde_json::Value> = out
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["type"], "entry");
+        assert_eq!(lines[0]["kind"], "node");
+        assert_eq!(lines[0]["root"], "/a");
+        assert_eq!(lines[0]["size"], 10);
+        assert_eq!(lines[0]["last_modified_epoch"], 1_700_000_000);
+        assert_eq!(lines[0]["last_modified"], "2023-11-14T22:13:20Z");
+        assert_eq!(lines[1]["type"], "total");
+        assert_eq!(lines[1]["count"], 1);
+        // Only known after the scan, so not part of the stream
+        assert!(lines[0].get("shared_with_entries").is_none());
+        assert!(lines[1].get("shared_with_entries").is_none());
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render(OutputFormat::Json, &[entry("/a/node_modules", 10)]))
+                .unwrap();
+        assert_eq!(json["entries"][0]["shared_with_entries"], 0);
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let out = render(
+            OutputFormat::Csv,
+            &[entry("/a,b/node_modules", 5), entry("/c/node_modules", 7)],
+        );
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "type,path,root,kind,size,apparent_size,shared_size,shared_with_entries,last_modified,last_modified_epoch,last_activity,last_activity_epoch,protected,link_target,name,version,private,workspaces,lockfile,orphaned,git_repo,last_commit,last_commit_epoch,git_dirty,git_tracked,in_use"
+        );
+        assert!(lines[1].starts_with("entry,\"/a,b/node_modules\",\"/a,b\",node,5,0,0,0,"));
+        assert!(lines[1].ends_with(",false,,,,false,,,false,false,,,false,false,"));
+        assert_eq!(lines[3], "total,,,,12,,0,0,,,,,,,,,,,,,,,,,,");
+    }
+}
@@ -0,0 +1,132 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A running process that uses files inside a project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// Short command name from `/proc/<pid>/comm`, e.g. `node`
+    pub name: String,
+}
+
+impl ProcessInfo {
+    /// `next-server (1234)`
+    pub fn label(&self) -> String {
+        format!("{} ({})", self.name, self.pid)
+    }
+}
+
+/// Snapshot of the paths running processes work in: their cwd, executable and
+/// open files. Only processes this user may inspect are included; on systems
+/// without `/proc` the table is empty.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessTable {
+    processes: Vec<(ProcessInfo, Vec<PathBuf>)>,
+}
+
+impl ProcessTable {
+    pub fn read() -> ProcessTable {
+        let Ok(proc_dir) = fs::read_dir("/proc") else {
+            return ProcessTable::default();
+        };
+        let own_pid = std::process::id();
+
+        let processes = proc_dir
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+            .filter(|&pid| pid != own_pid)
+            .filter_map(|pid| {
+                let dir = PathBuf::from(format!("/proc/{}", pid));
+                let paths = process_paths(&dir);
+                if paths.is_empty() {
+                    return None;
+                }
+                let name = fs::read_to_string(dir.join("comm"))
+                    .map(|comm| comm.trim().to_string())
+                    .unwrap_or_default();
+                Some((ProcessInfo { pid, name }, paths))
+            })
+            .collect();
+
+        ProcessTable { processes }
+    }
+
+    /// Processes with their cwd, executable or an open file inside `dir`
+    pub fn users_of(&self, dir: &Path) -> Vec<ProcessInfo> {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        self.processes
+            .iter()
+            .filter(|(_, paths)| paths.iter().any(|p| p.starts_with(&dir)))
+            .map(|(process, _)| process.clone())
+            .collect()
+    }
+}
+
+/// cwd, exe and the targets of open file descriptors that are real paths
+/// (sockets, pipes and anonymous inodes are skipped)
+fn process_paths(dir: &Path) -> Vec<PathBuf> {
+    let fds = fs::read_dir(dir.join("fd"))
+        .into_iter()
+        .flatten()
+        .filter_map(|fd| fd.ok())
+        .map(|fd| fd.path());
+
+    [dir.join("cwd"), dir.join("exe")]
+        .into_iter()
+        .chain(fds)
+        .filter_map(|link| fs::read_link(link).ok())
+        .filter(|target| target.is_absolute())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_users_of_matches_paths_inside_dir() {
+        let temp = tempdir().unwrap();
+        let project = temp.path().canonicalize().unwrap();
+        let table = ProcessTable {
+            processes: vec![
+                (
+                    ProcessInfo {
+                        pid: 10,
+                        name: "node".to_string(),
+                    },
+                    vec![project.join("node_modules").join(".bin").join("next")],
+                ),
+                (
+                    ProcessInfo {
+                        pid: 11,
+                        name: "bash".to_string(),
+                    },
+                    vec![PathBuf::from("/usr/bin/bash")],
+                ),
+            ],
+        };
+
+        let users = table.users_of(&project);
+
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].label(), "node (10)");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_finds_child_process_by_cwd() {
+        let temp = tempdir().unwrap();
+        let mut child = std::process::Command::new("sleep")
+            .arg("5")
+            .current_dir(temp.path())
+            .spawn()
+            .unwrap();
+
+        let users = ProcessTable::read().users_of(temp.path());
+        let _ = child.kill();
+        let _ = child.wait();
+
+        assert!(users.iter().any(|p| p.pid == child.id()));
+    }
+}
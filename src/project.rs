@@ -0,0 +1,151 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Lockfile found next to `package.json`, telling which package manager installed it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lockfile {
+    Npm,
+    Yarn,
+    Pnpm,
+    Bun,
+}
+
+impl Lockfile {
+    /// Checked in this order when a project has more than one lockfile
+    const ALL: [(Lockfile, &'static str); 4] = [
+        (Lockfile::Pnpm, "pnpm-lock.yaml"),
+        (Lockfile::Yarn, "yarn.lock"),
+        (Lockfile::Bun, "bun.lockb"),
+        (Lockfile::Npm, "package-lock.json"),
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Lockfile::Npm => "npm",
+            Lockfile::Yarn => "yarn",
+            Lockfile::Pnpm => "pnpm",
+            Lockfile::Bun => "bun",
+        }
+    }
+
+    pub fn detect(dir: &Path) -> Option<Lockfile> {
+        Self::ALL
+            .iter()
+            .find(|(_, name)| dir.join(name).is_file())
+            .map(|(lockfile, _)| *lockfile)
+    }
+}
+
+/// Details from the `package.json` next to an artifact directory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectInfo {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub private: bool,
+    /// Workspace globs, from either `"workspaces": [...]` or `"workspaces": {"packages": [...]}`
+    pub workspaces: Vec<String>,
+    pub lockfile: Option<Lockfile>,
+}
+
+impl ProjectInfo {
+    /// Read `dir/package.json`. Returns `None` when there is no `package.json`;
+    /// a file that isn't valid JSON still counts as a project, just without details.
+    pub fn read(dir: &Path) -> Option<ProjectInfo> {
+        let text = fs::read_to_string(dir.join("package.json")).ok()?;
+        let lockfile = Lockfile::detect(dir);
+        let Ok(json) = serde_json::from_str::<Value>(&text) else {
+            return Some(ProjectInfo {
+                lockfile,
+                ..ProjectInfo::default()
+            });
+        };
+
+        let string = |key: &str| json.get(key).and_then(Value::as_str).map(str::to_string);
+        let workspaces = json
+            .get("workspaces")
+            .map(|w| w.get("packages").unwrap_or(w))
+            .and_then(Value::as_array)
+            .map(|globs| {
+                globs
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(ProjectInfo {
+            name: string("name"),
+            version: string("version"),
+            private: json
+                .get("private")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            workspaces,
+            lockfile,
+        })
+    }
+
+    /// `name@version`, or whichever part is known
+    pub fn label(&self) -> Option<String> {
+        match (&self.name, &self.version) {
+            (Some(name), Some(version)) => Some(format!("{}@{}", name, version)),
+            (Some(name), None) => Some(name.clone()),
+            (None, Some(version)) => Some(format!("@{}", version)),
+            (None, None) => None,
+        }
+    }
+
+    /// ` [private, ws]` for a private package and/or a workspace root, empty otherwise
+    pub fn tags(&self) -> String {
+        let mut tags = Vec::new();
+        if self.private {
+            tags.push("private");
+        }
+        if !self.workspaces.is_empty() {
+            tags.push("ws");
+        }
+        if tags.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", tags.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_reads_package_json_and_lockfile() {
+        let temp = tempdir().unwrap();
+        fs::write(
+            temp.path().join("package.json"),
+            r#"{"name": "api", "version": "1.2.0", "private": true,
+                "workspaces": {"packages": ["packages/*"]}}"#,
+        )
+        .unwrap();
+        fs::write(temp.path().join("yarn.lock"), "").unwrap();
+
+        let info = ProjectInfo::read(temp.path()).unwrap();
+
+        assert_eq!(info.label().as_deref(), Some("api@1.2.0"));
+        assert!(info.private);
+        assert_eq!(info.workspaces, vec!["packages/*".to_string()]);
+        assert_eq!(info.lockfile, Some(Lockfile::Yarn));
+        assert_eq!(info.tags(), " [private, ws]");
+        assert_eq!(ProjectInfo::default().tags(), "");
+    }
+
+    #[test]
+    fn test_missing_or_broken_package_json() {
+        let temp = tempdir().unwrap();
+        assert_eq!(ProjectInfo::read(temp.path()), None);
+
+        fs::write(temp.path().join("package.json"), "{ not json").unwrap();
+        assert_eq!(ProjectInfo::read(temp.path()), Some(ProjectInfo::default()));
+    }
+}
@@ -1,19 +1,85 @@
 use anyhow::Result;
 use bytesize::ByteSize;
 use rayon::prelude::*;
-use std::fs;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, Metadata};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
+use crate::activity;
+use crate::cache::{self, ScanCache};
+use crate::detector::{self, Detector};
+use crate::exclude::ExcludeRules;
+use crate::git::{GitInfo, GitRepos};
+use crate::mounts;
+use crate::procs::{ProcessInfo, ProcessTable};
+use crate::project::ProjectInfo;
+use crate::trash;
+
+/// Marker file next to `package.json` (or `Cargo.toml`, ...) that keeps a project's artifacts
+pub const KEEP_FILE: &str = ".nm-keep";
+
 /// Type alias for the progress callback to reduce complexity
 pub type ProgressCallback = Arc<Mutex<dyn FnMut(&str) + Send>>;
 
-#[derive(Debug, Clone)]
+/// Called with every entry as soon as the scanner finds it
+pub type EntryCallback = Arc<Mutex<dyn FnMut(&NodeModulesEntry) + Send>>;
+
+/// Shared flag used to stop a running scan from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct NodeModulesEntry {
     pub path: PathBuf,
+    /// Scan root this entry was found under
+    pub root: PathBuf,
+    /// Detector kind that found this entry, e.g. `node` or `rust`
+    pub kind: &'static str,
+    /// Bytes on disk that deleting this entry would free
     pub size: u64,
+    /// Sum of file lengths, counting each hard-linked file once
+    pub apparent_size: u64,
+    /// Bytes on disk in files that are also hard-linked from outside the scanned entries
+    pub shared_size: u64,
+    /// Bytes on disk in files hard-linked only from this and other scanned entries;
+    /// freed once all of those entries are deleted
+    pub shared_with_entries: u64,
+    /// Hard-linked files with links outside this entry, see `classify_shared`
+    pub shared_links: Arc<Vec<SharedLink>>,
     pub last_modified: Option<SystemTime>,
+    /// When the project itself was last worked on, see `activity::last_activity`;
+    /// `None` as well when the scan skipped it, see `ScanOptions::activity`
+    pub last_activity: Option<SystemTime>,
+    /// Marked with a `.nm-keep` file or under a protected path; never deleted
+    pub protected: bool,
+    /// Set when the entry is a symlink; deleting it only removes the link
+    pub link_target: Option<PathBuf>,
+    /// From the sibling `package.json`, if there is one
+    pub project: Option<ProjectInfo>,
+    /// A `node_modules` without a `package.json` next to it
+    pub orphaned: bool,
+    /// Set when the project is inside a git repository
+    pub git: Option<GitInfo>,
+    /// Running processes working inside the project; deleting would break them
+    pub in_use: Vec<ProcessInfo>,
 }
 
 impl NodeModulesEntry {
@@ -21,117 +87,711 @@ impl NodeModulesEntry {
         ByteSize::b(self.size).to_string()
     }
 
+    /// Path as shown to the user; symlinks include their target
+    pub fn display_path(&self) -> String {
+        match &self.link_target {
+            Some(target) => format!("{} -> {}", self.path.display(), target.display()),
+            None => self.path.display().to_string(),
+        }
+    }
+
+    /// Size with the shared parts appended when there are any,
+    /// e.g. `12 MB + 240 MB shared + 8 MB shared with other entries`
+    pub fn size_summary(&self) -> String {
+        let mut summary = self.size_human();
+        if self.shared_size > 0 {
+            summary.push_str(&format!(" + {} shared", ByteSize::b(self.shared_size)));
+        }
+        if self.shared_with_entries > 0 {
+            summary.push_str(&format!(
+                " + {} shared with other entries",
+                ByteSize::b(self.shared_with_entries)
+            ));
+        }
+        summary
+    }
+
     pub fn last_modified_human(&self) -> String {
-        match self.last_modified {
-            Some(time) => {
-                if let Ok(duration) = time.elapsed() {
-                    let secs = duration.as_secs();
-                    if secs < 60 {
-                        format!("{}s ago", secs)
-                    } else if secs < 3600 {
-                        format!("{}m ago", secs / 60)
-                    } else if secs < 86400 {
-                        format!("{}h ago", secs / 3600)
-                    } else {
-                        format!("{}d ago", secs / 86400)
-                    }
+        age_human(self.last_modified)
+    }
+
+    pub fn last_activity_human(&self) -> String {
+        age_human(self.last_activity)
+    }
+
+    pub fn last_commit_human(&self) -> String {
+        age_human(self.git.as_ref().and_then(|git| git.last_commit))
+    }
+
+    /// Committed to git; deleting it would show up as a huge diff
+    pub fn is_tracked(&self) -> bool {
+        self.git.as_ref().is_some_and(|git| git.tracked)
+    }
+
+    /// First process using the project plus how many more, e.g. `node (1234) +2`
+    pub fn in_use_summary(&self) -> Option<String> {
+        let first = self.in_use.first()?;
+        Some(match self.in_use.len() {
+            1 => first.label(),
+            n => format!("{} +{}", first.label(), n - 1),
+        })
+    }
+
+    /// Look up the processes working inside this entry's project
+    pub fn refresh_in_use(&mut self, processes: &ProcessTable) {
+        self.in_use = self
+            .path
+            .parent()
+            .map(|dir| processes.users_of(dir))
+            .unwrap_or_default();
+    }
+
+    /// Time used by age filters: project activity when known, else the folder's mtime
+    pub fn stale_since(&self) -> Option<SystemTime> {
+        self.last_activity.or(self.last_modified)
+    }
+}
+
+/// `3d ago` style age of a timestamp
+fn age_human(time: Option<SystemTime>) -> String {
+    match time {
+        Some(time) => {
+            if let Ok(duration) = time.elapsed() {
+                let secs = duration.as_secs();
+                if secs < 60 {
+                    format!("{}s ago", secs)
+                } else if secs < 3600 {
+                    format!("{}m ago", secs / 60)
+                } else if secs < 86400 {
+                    format!("{}h ago", secs / 3600)
                 } else {
-                    "Unknown".to_string()
+                    format!("{}d ago", secs / 86400)
                 }
+            } else {
+                "Unknown".to_string()
             }
-            None => "Unknown".to_string(),
         }
+        None => "Unknown".to_string(),
     }
 }
 
-/// Scan for node_modules directories, only finding first-level occurrences.
-/// When a node_modules is found, we don't recurse into it to find nested ones.
-pub fn scan_for_node_modules(
-    root: &Path,
-    progress_callback: Option<ProgressCallback>,
-) -> Result<Vec<NodeModulesEntry>> {
-    let entries = Arc::new(Mutex::new(Vec::new()));
+/// What to look for while scanning and how to report it
+#[derive(Clone)]
+pub struct ScanOptions {
+    pub detectors: Vec<Arc<dyn Detector>>,
+    /// Gitignore-style `--exclude` patterns, anchored at the scan root
+    pub exclude: Vec<String>,
+    /// Artifacts under these (canonical) paths are reported as protected
+    pub protected_paths: Vec<PathBuf>,
+    /// Don't descend into directories on a different filesystem than the scan root
+    pub one_file_system: bool,
+    /// Mount points (network and pseudo filesystems) that are never descended into
+    pub skipped_mounts: Arc<HashSet<PathBuf>>,
+    /// Descend into symlinked directories; each directory is still visited only once
+    pub follow_symlinks: bool,
+    pub progress_callback: Option<ProgressCallback>,
+    /// Called with each entry while the scan is still running
+    pub entry_callback: Option<EntryCallback>,
+    /// When triggered the scan stops early and returns what was found so far
+    pub cancel: CancelToken,
+    /// Sizes and listings from earlier scans; `None` measures everything
+    pub cache: Option<Arc<ScanCache>>,
+    /// Look up project activity for every entry while scanning. It samples up to
+    /// a few hundred files per project, so callers that never show or filter on
+    /// it turn it off.
+    pub activity: bool,
+}
 
-    scan_directory(root, &entries, &progress_callback)?;
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            detectors: detector::default_detectors(),
+            exclude: Vec::new(),
+            protected_paths: Vec::new(),
+            one_file_system: false,
+            skipped_mounts: Arc::new(HashSet::new()),
+            follow_symlinks: false,
+            progress_callback: None,
+            entry_callback: None,
+            cancel: CancelToken::new(),
+            cache: None,
+            activity: true,
+        }
+    }
+}
 
-    let result = entries.lock().unwrap().clone();
+/// Scan for every artifact kind in `options.detectors`, only finding first-level occurrences.
+/// When an artifact directory is found, we don't recurse into it to find nested ones.
+pub fn scan_with_options(root: &Path, options: &ScanOptions) -> Result<Vec<NodeModulesEntry>> {
+    let entries = Mutex::new(Vec::new());
+    let rules = ExcludeRules::new(root, &options.exclude)?;
+    let context = ScanContext::new(root, &entries, options, true);
+
+    scan_directory(root, &context, &rules)?;
+
+    let result = entries.into_inner().unwrap();
     Ok(result)
 }
 
-fn scan_directory(
+/// Walk `root` like `scan_with_options` and return the artifact paths, without
+/// measuring or inspecting them. Callbacks still see every directory walked and
+/// every artifact found, but the entries only have their path, root, kind and link target.
+pub fn find_artifacts(root: &Path, options: &ScanOptions) -> Result<Vec<PathBuf>> {
+    let entries = Mutex::new(Vec::new());
+    let rules = ExcludeRules::new(root, &options.exclude)?;
+    let context = ScanContext::new(root, &entries, options, false);
+
+    scan_directory(root, &context, &rules)?;
+
+    let entries = entries.into_inner().unwrap();
+    Ok(entries.into_iter().map(|entry| entry.path).collect())
+}
+
+/// Scan `dir`, a directory somewhere below `root`, the way a full scan of `root` would
+/// see it: `--exclude` patterns and `.nmignore` files on the way down apply, and `dir`
+/// may itself be an artifact. Returns every entry at or below `dir`; nothing when `dir`
+/// is gone, excluded or not under `root`.
+pub fn scan_subtree(
+    root: &Path,
     dir: &Path,
-    entries: &Arc<Mutex<Vec<NodeModulesEntry>>>,
-    progress_callback: &Option<ProgressCallback>,
-) -> Result<()> {
-    if !dir.is_dir() {
+    options: &ScanOptions,
+) -> Result<Vec<NodeModulesEntry>> {
+    let entries = Mutex::new(Vec::new());
+    let context = ScanContext::new(root, &entries, options, true);
+    let mut rules = ExcludeRules::new(root, &options.exclude)?;
+
+    if dir == root {
+        scan_directory(root, &context, &rules)?;
+        return Ok(entries.into_inner().unwrap());
+    }
+    let Ok(relative) = dir.strip_prefix(root) else {
+        return Ok(Vec::new());
+    };
+    // Pick up the .nmignore files of every directory between root and dir
+    let mut ancestor = root.to_path_buf();
+    for component in relative.parent().into_iter().flat_map(Path::components) {
+        ancestor.push(component);
+        rules = rules.enter(&ancestor);
+    }
+
+    let Ok(metadata) = fs::symlink_metadata(dir) else {
+        return Ok(Vec::new());
+    };
+    let is_link = metadata.file_type().is_symlink();
+    if !dir.is_dir() || rules.is_excluded(dir) || context.is_foreign_mount(dir) {
+        return Ok(Vec::new());
+    }
+
+    if let Some(detector) = options.detectors.iter().find(|d| d.matches(dir)) {
+        report_artifact(dir.to_path_buf(), is_link, detector.as_ref(), &context);
+    } else if !is_link || options.follow_symlinks {
+        scan_directory(dir, &context, &rules.enter(dir))?;
+    }
+    Ok(entries.into_inner().unwrap())
+}
+
+/// State shared by every directory visited while scanning one root
+struct ScanContext<'a> {
+    root: &'a Path,
+    /// Device of `root` when the scan must stay on one filesystem
+    root_device: Option<u64>,
+    entries: &'a Mutex<Vec<NodeModulesEntry>>,
+    /// (device, inode) of directories already scanned, to break symlink loops
+    visited: Mutex<HashSet<(u64, u64)>>,
+    /// Running processes when the scan started, to mark projects in use
+    processes: ProcessTable,
+    /// Repositories read so far; workspaces of a monorepo share one
+    git: GitRepos,
+    /// Measure and inspect artifacts; `find_artifacts` only needs their paths
+    inspect: bool,
+    options: &'a ScanOptions,
+}
+
+impl<'a> ScanContext<'a> {
+    fn new(
+        root: &'a Path,
+        entries: &'a Mutex<Vec<NodeModulesEntry>>,
+        options: &'a ScanOptions,
+        inspect: bool,
+    ) -> Self {
+        ScanContext {
+            root,
+            root_device: options
+                .one_file_system
+                .then(|| mounts::device_id(root))
+                .flatten(),
+            entries,
+            visited: Mutex::new(HashSet::new()),
+            processes: if inspect {
+                ProcessTable::read()
+            } else {
+                ProcessTable::default()
+            },
+            git: GitRepos::default(),
+            inspect,
+            options,
+        }
+    }
+
+    /// Size of an artifact, taken from the cache while its tree mtime is unchanged
+    fn artifact_size(&self, path: &Path) -> (DirSize, Vec<SharedLink>) {
+        let Some(cache) = self.options.cache.as_deref() else {
+            return calculate_dir_size(path, &self.options.cancel);
+        };
+        let signature = cache::tree_mtime(path);
+        if let Some(measured) = cache.size(path, signature) {
+            return measured;
+        }
+        let (size, links) = calculate_dir_size(path, &self.options.cancel);
+        if !self.options.cancel.is_cancelled() {
+            cache.store_size(path, signature, size, &links);
+        }
+        (size, links)
+    }
+
+    /// Whether `dir` is on a filesystem the scan should not enter
+    fn is_foreign_mount(&self, dir: &Path) -> bool {
+        if self.options.skipped_mounts.contains(dir) {
+            return true;
+        }
+        match self.root_device {
+            Some(device) => mounts::device_id(dir).is_some_and(|d| d != device),
+            None => false,
+        }
+    }
+
+    /// Record `dir` as scanned; false if it was reached before through another path
+    fn first_visit(&self, dir: &Path) -> bool {
+        if !self.options.follow_symlinks {
+            // Without following links every directory is reached exactly once
+            return true;
+        }
+        match fs::metadata(dir).ok().and_then(|m| dir_id(&m)) {
+            Some(id) => self.visited.lock().unwrap().insert(id),
+            None => true,
+        }
+    }
+}
+
+/// Scan several roots one after another and merge the results.
+/// Overlapping roots are collapsed first so no folder is reported twice.
+pub fn scan_roots(roots: &[PathBuf], options: &ScanOptions) -> Result<Vec<NodeModulesEntry>> {
+    let mut seen = HashSet::new();
+    let mut merged = Vec::new();
+
+    for root in normalize_roots(roots) {
+        if options.cancel.is_cancelled() {
+            break;
+        }
+        for entry in scan_with_options(&root, options)? {
+            if seen.insert(entry.path.clone()) {
+                merged.push(entry);
+            }
+        }
+    }
+
+    classify_shared(&mut merged);
+    Ok(merged)
+}
+
+/// Canonicalize `roots`, then drop duplicates and roots nested inside another root
+pub fn normalize_roots(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut sorted: Vec<PathBuf> = roots
+        .iter()
+        .map(|root| root.canonicalize().unwrap_or_else(|_| root.clone()))
+        .collect();
+    // Parents sort before their children
+    sorted.sort();
+    sorted.dedup();
+
+    let mut normalized: Vec<PathBuf> = Vec::new();
+    for root in sorted {
+        if !normalized.iter().any(|outer| root.starts_with(outer)) {
+            normalized.push(root);
+        }
+    }
+    normalized
+}
+
+fn scan_directory(dir: &Path, context: &ScanContext, rules: &ExcludeRules) -> Result<()> {
+    let options = context.options;
+    let cancel = &options.cancel;
+    if cancel.is_cancelled() || !dir.is_dir() || !context.first_visit(dir) {
         return Ok(());
     }
 
     // Update progress
-    if let Some(callback) = progress_callback {
+    if let Some(callback) = &options.progress_callback {
         if let Ok(mut cb) = callback.lock() {
             cb(dir.to_string_lossy().as_ref());
         }
     }
 
-    let read_dir = match fs::read_dir(dir) {
-        Ok(rd) => rd,
-        Err(_) => return Ok(()), // Skip directories we can't read
+    let subdirs = match &options.cache {
+        Some(cache) => cache.subdirs(dir, modified(dir), || list_subdirs(dir)),
+        None => list_subdirs(dir),
     };
 
-    let subdirs: Vec<PathBuf> = read_dir
-        .filter_map(|entry| entry.ok())
-        .map(|entry| entry.path())
-        .filter(|path| path.is_dir())
-        .collect();
-
-    // Check if any subdirectory is node_modules
+    // Check if any subdirectory is a build artifact
     let mut dirs_to_recurse = Vec::new();
 
-    for path in subdirs {
-        if path
-            .file_name()
-            .map(|n| n == "node_modules")
-            .unwrap_or(false)
-        {
-            // Found a node_modules directory - add it and DON'T recurse into it
-            let size = calculate_dir_size(&path);
-            let last_modified = path.metadata().ok().and_then(|m| m.modified().ok());
-
-            entries.lock().unwrap().push(NodeModulesEntry {
-                path,
-                size,
-                last_modified,
-            });
-        } else {
-            // Not a node_modules - we should recurse into it
+    for (path, is_link) in subdirs {
+        if cancel.is_cancelled() {
+            return Ok(());
+        }
+
+        // Excluded directories are neither reported nor searched
+        if rules.is_excluded(&path) || context.is_foreign_mount(&path) {
+            continue;
+        }
+
+        if let Some(detector) = options.detectors.iter().find(|d| d.matches(&path)) {
+            // Found an artifact directory - add it and DON'T recurse into it
+            if !report_artifact(path, is_link, detector.as_ref(), context) {
+                return Ok(());
+            }
+        } else if (!is_link || options.follow_symlinks) && !detector::is_builtin_artifact(&path) {
+            // Not an artifact of any kind, selected or not - we should recurse into it
             dirs_to_recurse.push(path);
         }
     }
 
-    // Recurse into non-node_modules directories in parallel
+    // Recurse into non-artifact directories in parallel
     dirs_to_recurse.par_iter().for_each(|path| {
-        let _ = scan_directory(path, entries, progress_callback);
+        let _ = scan_directory(path, context, &rules.enter(path));
     });
 
     Ok(())
 }
 
-fn calculate_dir_size(path: &Path) -> u64 {
-    walkdir::WalkDir::new(path)
+/// Measure an artifact directory and add it to the results.
+/// A symlinked one is reported as a link; removing it frees nothing.
+/// Returns false when the scan was cancelled before the size was known.
+fn report_artifact(
+    path: PathBuf,
+    is_link: bool,
+    detector: &dyn Detector,
+    context: &ScanContext,
+) -> bool {
+    let options = context.options;
+    if !context.inspect {
+        let entry = NodeModulesEntry {
+            link_target: is_link.then(|| fs::read_link(&path).ok()).flatten(),
+            path,
+            root: context.root.to_path_buf(),
+            kind: detector.kind(),
+            ..NodeModulesEntry::default()
+        };
+        return push_entry(entry, context);
+    }
+
+    let last_modified = modified(&path);
+    let ((size, shared_links), link_target) = if is_link {
+        ((DirSize::default(), Vec::new()), fs::read_link(&path).ok())
+    } else {
+        (context.artifact_size(&path), None)
+    };
+    if options.cancel.is_cancelled() {
+        // The size is incomplete - don't report a half-measured entry
+        return false;
+    }
+    let protected = is_protected(&path, &options.protected_paths);
+    let project = path.parent().and_then(ProjectInfo::read);
+    // Other kinds (.venv, target, ...) don't need a package.json
+    let orphaned = project.is_none() && detector.kind() == "node";
+    let git = context.git.info(&path);
+
+    let mut entry = NodeModulesEntry {
+        path,
+        root: context.root.to_path_buf(),
+        kind: detector.kind(),
+        size: size.allocated,
+        apparent_size: size.apparent,
+        shared_size: size.shared,
+        shared_with_entries: 0,
+        shared_links: Arc::new(shared_links),
+        last_modified,
+        last_activity: None,
+        protected,
+        link_target,
+        project,
+        orphaned,
+        git,
+        in_use: Vec::new(),
+    };
+    entry.refresh_in_use(&context.processes);
+    if options.activity {
+        entry.last_activity = entry
+            .path
+            .parent()
+            .and_then(|project| activity::last_activity(project, &entry.root));
+    }
+    push_entry(entry, context)
+}
+
+/// Hand a found entry to the entry callback and add it to the results
+fn push_entry(entry: NodeModulesEntry, context: &ScanContext) -> bool {
+    if let Some(callback) = &context.options.entry_callback {
+        if let Ok(mut cb) = callback.lock() {
+            cb(&entry);
+        }
+    }
+
+    context.entries.lock().unwrap().push(entry);
+    true
+}
+
+/// (path, is symlink) for every directory and symlink to a directory in `dir`.
+/// Directories we can't read have no subdirectories.
+fn list_subdirs(dir: &Path) -> Vec<(PathBuf, bool)> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    read_dir
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_type = entry.file_type().ok()?;
+            let path = entry.path();
+            if file_type.is_dir() {
+                Some((path, false))
+            } else if file_type.is_symlink() && path.is_dir() {
+                Some((path, true))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata().ok().and_then(|m| m.modified().ok())
+}
+
+/// An artifact is protected when its project has a `.nm-keep` file
+/// or it lies under one of `protected_paths`
+fn is_protected(path: &Path, protected_paths: &[PathBuf]) -> bool {
+    if path.parent().is_some_and(|p| p.join(KEEP_FILE).exists()) {
+        return true;
+    }
+    if protected_paths.is_empty() {
+        return false;
+    }
+    fs::canonicalize(path)
+        .map(|real| protected_paths.iter().any(|p| real.starts_with(p)))
+        .unwrap_or(false)
+}
+
+/// Disk usage of one artifact directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirSize {
+    /// Sum of file lengths, counting each hard-linked file once
+    pub apparent: u64,
+    /// Allocated bytes (`st_blocks * 512`) that deleting the directory would free
+    pub allocated: u64,
+    /// Allocated bytes of hard-linked files that also have links outside the directory
+    pub shared: u64,
+}
+
+/// A hard-linked file measured inside one artifact that also has links elsewhere
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedLink {
+    /// (device, inode)
+    pub id: (u64, u64),
+    /// Links to the file inside the artifact
+    pub links: u64,
+    /// All links to the file (`st_nlink`)
+    pub nlink: u64,
+    pub allocated: u64,
+}
+
+/// Split the shared bytes of `entries` into files whose other links all lie in
+/// other entries of the list and files that are also linked from elsewhere
+/// (a pnpm store, say). Entries measured on their own count everything as the latter.
+pub fn classify_shared(entries: &mut [NodeModulesEntry]) {
+    let links = links_by_file(entries.iter());
+    for entry in entries.iter_mut() {
+        let (with_entries, outside): (Vec<&SharedLink>, Vec<&SharedLink>) = entry
+            .shared_links
+            .iter()
+            .partition(|link| links[&link.id] >= link.nlink);
+        entry.shared_with_entries = with_entries.iter().map(|link| link.allocated).sum();
+        entry.shared_size = outside.iter().map(|link| link.allocated).sum();
+    }
+}
+
+/// Bytes deleting all of `entries` together would free: their own sizes plus the
+/// files whose links all lie within the set
+pub fn combined_size<'a>(entries: impl Iterator<Item = &'a NodeModulesEntry> + Clone) -> u64 {
+    let own: u64 = entries.clone().map(|entry| entry.size).sum();
+    let links = links_by_file(entries.clone());
+    let mut counted = HashSet::new();
+    let shared: u64 = entries
+        .flat_map(|entry| entry.shared_links.iter())
+        .filter(|link| links[&link.id] >= link.nlink && counted.insert(link.id))
+        .map(|link| link.allocated)
+        .sum();
+    own + shared
+}
+
+/// How many links to each shared file the entries hold between them
+fn links_by_file<'a>(
+    entries: impl Iterator<Item = &'a NodeModulesEntry>,
+) -> HashMap<(u64, u64), u64> {
+    let mut links = HashMap::new();
+    for link in entries.flat_map(|entry| entry.shared_links.iter()) {
+        *links.entry(link.id).or_insert(0) += link.links;
+    }
+    links
+}
+
+/// Measure a directory tree. Hard-linked files (as installed by pnpm) are counted once,
+/// and only count as freeable when every link to them lives inside `path`; the others
+/// are returned so `classify_shared` can tell which links other entries hold.
+pub fn calculate_dir_size(path: &Path, cancel: &CancelToken) -> (DirSize, Vec<SharedLink>) {
+    let mut size = DirSize::default();
+    // (device, inode) -> (link count, links seen, allocated bytes)
+    let mut linked: HashMap<(u64, u64), (u64, u64, u64)> = HashMap::new();
+
+    for entry in walkdir::WalkDir::new(path)
         .into_iter()
+        .take_while(|_| !cancel.is_cancelled())
         .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter_map(|e| e.metadata().ok())
-        .map(|m| m.len())
-        .sum()
+    {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let allocated = allocated_size(&metadata);
+
+        if !metadata.is_file() {
+            // Directories and symlinks take up blocks too
+            size.allocated += allocated;
+            continue;
+        }
+
+        match hard_link_id(&metadata) {
+            Some((id, nlink)) => {
+                let link = linked.entry(id).or_insert((nlink, 0, allocated));
+                if link.1 == 0 {
+                    size.apparent += metadata.len();
+                }
+                link.1 += 1;
+            }
+            None => {
+                size.apparent += metadata.len();
+                size.allocated += allocated;
+            }
+        }
+    }
+
+    let mut shared_links = Vec::new();
+    for (&id, &(nlink, seen, allocated)) in &linked {
+        if seen >= nlink {
+            size.allocated += allocated;
+        } else {
+            size.shared += allocated;
+            shared_links.push(SharedLink {
+                id,
+                links: seen,
+                nlink,
+                allocated,
+            });
+        }
+    }
+
+    (size, shared_links)
+}
+
+#[cfg(unix)]
+fn allocated_size(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_size(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
+
+/// `((device, inode), link count)` for files with more than one hard link
+#[cfg(unix)]
+fn hard_link_id(metadata: &Metadata) -> Option<((u64, u64), u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.nlink() > 1).then(|| ((metadata.dev(), metadata.ino()), metadata.nlink()))
+}
+
+/// `(device, inode)` identifying a directory
+#[cfg(unix)]
+fn dir_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(not(unix))]
+fn hard_link_id(_metadata: &Metadata) -> Option<((u64, u64), u64)> {
+    None
+}
+
+/// Bytes removing a file or directory gives back, as `calculate_dir_size` counts
+/// them: the allocated blocks, but for a hard-linked file only once its last link
+/// goes, so each file is counted once however many of its links are removed.
+pub fn freed_by_unlink(metadata: &Metadata) -> u64 {
+    if metadata.is_file() && hard_link_id(metadata).is_some() {
+        0
+    } else {
+        allocated_size(metadata)
+    }
 }
 
-pub fn delete_node_modules(path: &Path) -> Result<()> {
-    fs::remove_dir_all(path)?;
+/// How selected folders are removed from disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeleteMode {
+    /// Remove permanently with `remove_dir_all`
+    #[default]
+    Permanent,
+    /// Move to the freedesktop.org trash so it can be restored
+    Trash,
+}
+
+impl DeleteMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DeleteMode::Permanent => "Permanent delete",
+            DeleteMode::Trash => "Move to trash",
+        }
+    }
+
+    pub fn toggle(&self) -> Self {
+        match self {
+            DeleteMode::Permanent => DeleteMode::Trash,
+            DeleteMode::Trash => DeleteMode::Permanent,
+        }
+    }
+}
+
+/// Remove an artifact directory. A symlink is only unlinked; its target is left alone.
+pub fn delete_node_modules(path: &Path, mode: DeleteMode) -> Result<()> {
+    match mode {
+        DeleteMode::Permanent if is_symlink(path) => fs::remove_file(path)?,
+        DeleteMode::Permanent => fs::remove_dir_all(path)?,
+        DeleteMode::Trash => {
+            trash::move_to_trash(path)?;
+        }
+    }
     Ok(())
 }
 
+pub fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .map(|m| m.file_type().is_symlink())
+        .unwrap_or(false)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -147,7 +807,7 @@ mod tests {
 
         fs::create_dir_all(&nested_nm).unwrap();
 
-        let results = scan_for_node_modules(temp.path(), None).unwrap();
+        let results = scan_with_options(temp.path(), &ScanOptions::default()).unwrap();
 
         // Should only find project1/node_modules, not the nested one
         assert_eq!(results.len(), 1);
@@ -164,8 +824,317 @@ mod tests {
         fs::create_dir_all(&project1_nm).unwrap();
         fs::create_dir_all(&project2_nm).unwrap();
 
-        let results = scan_for_node_modules(temp.path(), None).unwrap();
+        let results = scan_with_options(temp.path(), &ScanOptions::default()).unwrap();
+
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_cancelled_scan_stops_early() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("project1").join("node_modules")).unwrap();
+
+        let options = ScanOptions::default();
+        options.cancel.cancel();
+
+        let results = scan_with_options(temp.path(), &options).unwrap();
+
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_during_scan_keeps_only_complete_entries() {
+        let temp = tempdir().unwrap();
+        let projects = 200;
+        for i in 0..projects {
+            let dep = temp
+                .path()
+                .join(format!("project{}", i))
+                .join("node_modules")
+                .join("dep");
+            fs::create_dir_all(&dep).unwrap();
+            for file in 0..5 {
+                fs::write(dep.join(format!("{}.js", file)), "x".repeat(100)).unwrap();
+            }
+        }
+
+        let mut options = ScanOptions::default();
+        let cancel = options.cancel.clone();
+        let reported = Arc::new(Mutex::new(0));
+        let counter = Arc::clone(&reported);
+        options.entry_callback = Some(Arc::new(Mutex::new(move |_: &NodeModulesEntry| {
+            *counter.lock().unwrap() += 1;
+            cancel.cancel();
+        })));
+
+        let results = scan_with_options(temp.path(), &options).unwrap();
+
+        assert!(!results.is_empty());
+        assert!(results.len() < projects);
+        assert_eq!(results.len(), *reported.lock().unwrap());
+        // Artifacts being measured when the scan stopped are dropped, not cut short
+        assert!(results.iter().all(|entry| entry.apparent_size == 500));
+    }
+
+    #[test]
+    fn test_unselected_kinds_are_not_entered() {
+        let temp = tempdir().unwrap();
+        let app = temp.path().join("app");
+        let package_dist = app.join("node_modules").join("lodash").join("dist");
+        fs::create_dir_all(&package_dist).unwrap();
+        fs::write(package_dist.parent().unwrap().join("package.json"), "{}").unwrap();
+        fs::write(app.join("package.json"), "{}").unwrap();
+        fs::create_dir_all(app.join("dist")).unwrap();
+        // A virtualenv without a Python project file next to it is still not searched
+        let venv_dist = temp
+            .path()
+            .join("tool")
+            .join(".venv")
+            .join("lib")
+            .join("dist");
+        fs::create_dir_all(&venv_dist).unwrap();
+        fs::write(venv_dist.parent().unwrap().join("package.json"), "{}").unwrap();
+
+        let options = ScanOptions {
+            detectors: detector::detectors_for(&["dist"]),
+            ..ScanOptions::default()
+        };
+        let results = scan_with_options(temp.path(), &options).unwrap();
+
+        let paths: Vec<_> = results.iter().map(|e| e.path.clone()).collect();
+        assert_eq!(paths, vec![app.join("dist")]);
+    }
+
+    #[test]
+    fn test_scans_selected_kinds() {
+        let temp = tempdir().unwrap();
+        let crate_dir = temp.path().join("crate");
+        fs::create_dir_all(crate_dir.join("target")).unwrap();
+        fs::write(crate_dir.join("Cargo.toml"), "[package]").unwrap();
+        fs::create_dir_all(temp.path().join("web").join("node_modules")).unwrap();
+        // A `target` without Cargo.toml is not a Rust build directory
+        fs::create_dir_all(temp.path().join("other").join("target")).unwrap();
+
+        let options = ScanOptions {
+            detectors: detector::detectors_for(&["node", "rust"]),
+            ..ScanOptions::default()
+        };
+        let mut results = scan_with_options(temp.path(), &options).unwrap();
+        results.sort_by(|a, b| a.path.cmp(&b.path));
 
         assert_eq!(results.len(), 2);
+        assert_eq!(results[0].kind, "rust");
+        assert_eq!(results[0].path, crate_dir.join("target"));
+        assert!(!results[0].orphaned);
+        assert_eq!(results[1].kind, "node");
+        assert!(results[1].orphaned);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hard_links_are_counted_once() {
+        let temp = tempdir().unwrap();
+        let nm = temp.path().join("node_modules");
+        fs::create_dir_all(&nm).unwrap();
+
+        // Two links inside the entry: counted once and freeable
+        fs::write(nm.join("a.js"), vec![b'a'; 5000]).unwrap();
+        fs::hard_link(nm.join("a.js"), nm.join("a-copy.js")).unwrap();
+        // Linked from a store outside the entry: shared, not freeable
+        fs::write(temp.path().join("store.js"), vec![b'b'; 9000]).unwrap();
+        fs::hard_link(temp.path().join("store.js"), nm.join("b.js")).unwrap();
+
+        let (size, links) = calculate_dir_size(&nm, &CancelToken::new());
+
+        assert_eq!(size.apparent, 14000);
+        assert!(size.shared >= 9000);
+        assert!(size.allocated >= 5000);
+        assert_eq!(links.len(), 1);
+
+        // The shared file contributes nothing to the freeable size
+        fs::remove_file(nm.join("b.js")).unwrap();
+        let (without_shared, _) = calculate_dir_size(&nm, &CancelToken::new());
+        assert_eq!(without_shared.allocated, size.allocated);
+        assert_eq!(without_shared.shared, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_links_between_entries_are_classified_separately() {
+        let temp = tempdir().unwrap();
+        let first = temp.path().join("a").join("node_modules");
+        let second = temp.path().join("b").join("node_modules");
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        // Linked between the two entries only
+        fs::write(first.join("dep.js"), vec![b'a'; 8000]).unwrap();
+        fs::hard_link(first.join("dep.js"), second.join("dep.js")).unwrap();
+        // Also linked from a store outside the scan
+        let store = tempdir().unwrap();
+        fs::write(store.path().join("lib.js"), vec![b'b'; 8000]).unwrap();
+        fs::hard_link(store.path().join("lib.js"), first.join("lib.js")).unwrap();
+
+        let mut entries =
+            scan_roots(&[temp.path().to_path_buf()], &ScanOptions::default()).unwrap();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert!(entries[0].shared_with_entries >= 8000);
+        assert!(entries[0].shared_size >= 8000);
+        assert_eq!(
+            entries[1].shared_with_entries,
+            entries[0].shared_with_entries
+        );
+        assert_eq!(entries[1].shared_size, 0);
+
+        // Deleting both frees the file they share, counted once
+        let own: u64 = entries.iter().map(|e| e.size).sum();
+        assert_eq!(
+            combined_size(entries.iter()),
+            own + entries[1].shared_with_entries
+        );
+        assert_eq!(combined_size(entries[..1].iter()), entries[0].size);
+    }
+
+    #[test]
+    fn test_excluded_directories_are_skipped() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("app").join("node_modules")).unwrap();
+        fs::create_dir_all(temp.path().join("vendor").join("node_modules")).unwrap();
+        let fixtures = temp.path().join("tests").join("fixtures");
+        fs::create_dir_all(fixtures.join("node_modules")).unwrap();
+        fs::write(temp.path().join("tests").join(".nmignore"), "fixtures\n").unwrap();
+
+        let options = ScanOptions {
+            exclude: vec!["vendor".to_string()],
+            ..ScanOptions::default()
+        };
+        let results = scan_with_options(temp.path(), &options).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].path,
+            temp.path().join("app").join("node_modules")
+        );
+    }
+
+    #[test]
+    fn test_subtree_scan_matches_full_scan() {
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        let app_nm = root.join("app").join("node_modules");
+        fs::create_dir_all(&app_nm).unwrap();
+        let fixtures = root.join("tests").join("fixtures");
+        fs::create_dir_all(fixtures.join("node_modules")).unwrap();
+        fs::write(root.join("tests").join(".nmignore"), "fixtures\n").unwrap();
+        let options = ScanOptions::default();
+
+        // The directory itself can be the artifact
+        let results = scan_subtree(root, &app_nm, &options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].root, root);
+
+        // .nmignore files above the subtree still apply
+        assert!(scan_subtree(root, &fixtures, &options).unwrap().is_empty());
+        assert!(scan_subtree(root, &root.join("gone"), &options)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_keep_file_and_protected_paths() {
+        let temp = tempdir().unwrap();
+        let demo = temp.path().join("demo");
+        fs::create_dir_all(demo.join("node_modules")).unwrap();
+        fs::write(demo.join(KEEP_FILE), "").unwrap();
+        let airgap = temp.path().join("airgap");
+        fs::create_dir_all(airgap.join("node_modules")).unwrap();
+        fs::create_dir_all(temp.path().join("web").join("node_modules")).unwrap();
+
+        let options = ScanOptions {
+            protected_paths: vec![fs::canonicalize(&airgap).unwrap()],
+            ..ScanOptions::default()
+        };
+        let mut results = scan_with_options(temp.path(), &options).unwrap();
+        results.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let protected: Vec<bool> = results.iter().map(|e| e.protected).collect();
+        assert_eq!(protected, vec![true, true, false]);
+    }
+
+    #[test]
+    fn test_overlapping_roots_are_merged() {
+        let temp = tempdir().unwrap();
+        let work = temp.path().join("work");
+        let oss = temp.path().join("oss");
+        fs::create_dir_all(work.join("api").join("node_modules")).unwrap();
+        fs::create_dir_all(oss.join("lib").join("node_modules")).unwrap();
+
+        let roots = vec![work.clone(), oss.clone(), work.join("api"), work.clone()];
+        let mut results = scan_roots(&roots, &ScanOptions::default()).unwrap();
+        results.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].root, oss.canonicalize().unwrap());
+        assert_eq!(results[1].root, work.canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_skipped_mounts_are_not_entered() {
+        let temp = tempdir().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        let nas = root.join("nas");
+        fs::create_dir_all(nas.join("project").join("node_modules")).unwrap();
+        fs::create_dir_all(root.join("local").join("node_modules")).unwrap();
+
+        let options = ScanOptions {
+            skipped_mounts: Arc::new(HashSet::from([nas])),
+            one_file_system: true,
+            ..ScanOptions::default()
+        };
+        let results = scan_with_options(&root, &options).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, root.join("local").join("node_modules"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_not_followed_by_default() {
+        use std::os::unix::fs::symlink;
+
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        let store = root.join("store").join("node_modules");
+        fs::create_dir_all(&store).unwrap();
+        fs::write(store.join("index.js"), "x").unwrap();
+        // A workspace whose node_modules links to the shared store
+        fs::create_dir_all(root.join("app")).unwrap();
+        symlink(&store, root.join("app").join("node_modules")).unwrap();
+        // A symlinked project and a loop back to the root
+        symlink(root.join("store"), root.join("store-link")).unwrap();
+        symlink(root, root.join("app").join("loop")).unwrap();
+
+        let mut results = scan_with_options(root, &ScanOptions::default()).unwrap();
+        results.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].link_target, Some(store.clone()));
+        assert_eq!(results[0].size, 0);
+        assert_eq!(results[1].path, store);
+
+        let options = ScanOptions {
+            follow_symlinks: true,
+            ..ScanOptions::default()
+        };
+        // Following links terminates and still reports the store only once
+        let results = scan_with_options(root, &options).unwrap();
+        let real: Vec<_> = results.iter().filter(|e| e.link_target.is_none()).collect();
+        assert_eq!(real.len(), 1);
+
+        delete_node_modules(
+            &root.join("app").join("node_modules"),
+            DeleteMode::Permanent,
+        )
+        .unwrap();
+        assert!(store.join("index.js").exists());
     }
 }
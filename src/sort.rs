@@ -0,0 +1,103 @@
+use std::cmp::Ordering;
+
+use crate::scanner::NodeModulesEntry;
+
+/// Order in which entries are listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SortKey {
+    /// Largest first
+    #[default]
+    Size,
+    /// Least recently modified first
+    Age,
+    /// Alphabetical by path
+    Path,
+}
+
+impl SortKey {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortKey::Size => "size",
+            SortKey::Age => "age",
+            SortKey::Path => "path",
+        }
+    }
+
+    /// The next key when cycling with `s` in the TUI
+    pub fn next(&self) -> Self {
+        match self {
+            SortKey::Size => SortKey::Age,
+            SortKey::Age => SortKey::Path,
+            SortKey::Path => SortKey::Size,
+        }
+    }
+}
+
+/// Compare two entries by `key`. Ties are broken by path so the order is stable between runs.
+pub fn compare(
+    a: &NodeModulesEntry,
+    b: &NodeModulesEntry,
+    key: SortKey,
+    reverse: bool,
+) -> Ordering {
+    let ordering = match key {
+        SortKey::Size => b.size.cmp(&a.size),
+        // Entries with an unknown modification time go last
+        SortKey::Age => match (a.last_modified, b.last_modified) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        },
+        SortKey::Path => Ordering::Equal,
+    }
+    .then_with(|| a.path.cmp(&b.path));
+
+    if reverse {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+pub fn sort_entries(entries: &mut [NodeModulesEntry], key: SortKey, reverse: bool) {
+    entries.sort_by(|a, b| compare(a, b, key, reverse));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn entry(path: &str, size: u64, mtime: u64) -> NodeModulesEntry {
+        NodeModulesEntry {
+            path: PathBuf::from(path),
+            size,
+            last_modified: Some(UNIX_EPOCH + Duration::from_secs(mtime)),
+            ..Default::default()
+        }
+    }
+
+    fn paths(entries: &[NodeModulesEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.path.to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_sort_by_each_key() {
+        let mut entries = vec![
+            entry("/b", 10, 300),
+            entry("/a", 30, 200),
+            entry("/c", 20, 100),
+        ];
+
+        sort_entries(&mut entries, SortKey::Size, false);
+        assert_eq!(paths(&entries), vec!["/a", "/c", "/b"]);
+
+        sort_entries(&mut entries, SortKey::Age, false);
+        assert_eq!(paths(&entries), vec!["/c", "/a", "/b"]);
+
+        sort_entries(&mut entries, SortKey::Path, true);
+        assert_eq!(paths(&entries), vec!["/c", "/b", "/a"]);
+    }
+}
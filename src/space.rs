@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Free space reclaimed on one filesystem by a deletion run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreedSpace {
+    pub mount_point: PathBuf,
+    /// Sum of the scanned sizes of the folders that were deleted
+    pub estimated: u64,
+    /// Growth of the filesystem's free space, as reported by statvfs
+    pub actual: u64,
+}
+
+impl FreedSpace {
+    /// True when far less space came back than expected, e.g. because of
+    /// open file handles, snapshots or hard links
+    pub fn is_short(&self) -> bool {
+        self.actual < self.estimated / 2
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{}: freed {} (estimated {})",
+            self.mount_point.display(),
+            bytesize::ByteSize::b(self.actual),
+            bytesize::ByteSize::b(self.estimated)
+        )
+    }
+}
+
+struct Filesystem {
+    mount_point: PathBuf,
+    free_before: u64,
+}
+
+/// Samples the free space of every filesystem touched by a deletion so the
+/// real reclaimed bytes can be reported afterwards.
+#[derive(Default)]
+pub struct SpaceTracker {
+    filesystems: Vec<Filesystem>,
+    /// Folder -> (index into `filesystems`, scanned size)
+    folders: HashMap<PathBuf, (usize, u64)>,
+}
+
+impl SpaceTracker {
+    /// Record the free space of each filesystem holding one of `items` (path, size).
+    /// Must be called before anything is deleted.
+    pub fn start(items: &[(PathBuf, u64)]) -> Self {
+        let mut tracker = Self::default();
+        let mut by_device: HashMap<u64, usize> = HashMap::new();
+
+        for (path, size) in items {
+            let Some(device) = device_of(path) else {
+                continue;
+            };
+            let index = match by_device.get(&device) {
+                Some(&index) => index,
+                None => {
+                    let mount_point = mount_point(path, device);
+                    let Some(free_before) = free_space(&mount_point) else {
+                        continue;
+                    };
+                    tracker.filesystems.push(Filesystem {
+                        mount_point,
+                        free_before,
+                    });
+                    by_device.insert(device, tracker.filesystems.len() - 1);
+                    tracker.filesystems.len() - 1
+                }
+            };
+            tracker.folders.insert(path.clone(), (index, *size));
+        }
+
+        tracker
+    }
+
+    /// Sample free space again and report, per filesystem, what `deleted` actually freed.
+    /// Filesystems where nothing was deleted are left out.
+    pub fn finish(&self, deleted: &[PathBuf]) -> Vec<FreedSpace> {
+        let mut estimated = vec![0u64; self.filesystems.len()];
+        let mut touched = vec![false; self.filesystems.len()];
+        for path in deleted {
+            if let Some(&(index, size)) = self.folders.get(path) {
+                estimated[index] += size;
+                touched[index] = true;
+            }
+        }
+
+        self.filesystems
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| touched[*i])
+            .filter_map(|(i, fs)| {
+                let free_after = free_space(&fs.mount_point)?;
+                Some(FreedSpace {
+                    mount_point: fs.mount_point.clone(),
+                    estimated: estimated[i],
+                    actual: free_after.saturating_sub(fs.free_before),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(unix)]
+fn device_of(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::symlink_metadata(path).ok().map(|m| m.dev())
+}
+
+#[cfg(not(unix))]
+fn device_of(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(unix)]
+fn mount_point(path: &Path, device: u64) -> PathBuf {
+    crate::trash::mount_point(path, device)
+}
+
+#[cfg(not(unix))]
+fn mount_point(path: &Path, _device: u64) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Free bytes on the filesystem containing `path`, including blocks reserved for root
+#[cfg(unix)]
+fn free_space(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `c_path` is a valid NUL-terminated string and `stat` is a writable statvfs
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bfree as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tracker_reports_deleted_filesystems_only() {
+        let temp = tempdir().unwrap();
+        let kept = temp.path().join("kept").join("node_modules");
+        let gone = temp.path().join("gone").join("node_modules");
+        fs::create_dir_all(&kept).unwrap();
+        fs::create_dir_all(&gone).unwrap();
+        fs::write(gone.join("big.js"), vec![0u8; 64 * 1024]).unwrap();
+
+        let tracker = SpaceTracker::start(&[(kept.clone(), 10), (gone.clone(), 64 * 1024)]);
+        fs::remove_dir_all(&gone).unwrap();
+        let freed = tracker.finish(&[gone]);
+
+        assert_eq!(freed.len(), 1);
+        assert_eq!(freed[0].estimated, 64 * 1024);
+        assert!(tracker.finish(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_short_when_much_less_was_freed() {
+        let freed = FreedSpace {
+            mount_point: PathBuf::from("/"),
+            estimated: 1000,
+            actual: 100,
+        };
+        assert!(freed.is_short());
+        assert!(!FreedSpace {
+            actual: 900,
+            ..freed
+        }
+        .is_short());
+    }
+}
@@ -0,0 +1,85 @@
+use ratatui::style::Color;
+use serde::Deserialize;
+
+/// Color scheme for the TUI, chosen with `--theme` or `theme` in the config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    /// Bright colors for dark terminals
+    #[default]
+    Dark,
+    /// Darker colors that stay readable on light backgrounds
+    Light,
+    /// No colors, only the terminal's default foreground
+    Mono,
+}
+
+/// Colors used by the UI, looked up by role rather than by hue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub accent: Color,
+    pub accent_alt: Color,
+    pub text: Color,
+    pub muted: Color,
+    pub dim: Color,
+    pub warn: Color,
+    pub danger: Color,
+    pub ok: Color,
+    pub kind: Color,
+    /// Text drawn on top of `ok`/`danger` buttons
+    pub on_accent: Color,
+    /// Background of the row under the cursor
+    pub highlight: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        ThemeName::default().palette()
+    }
+}
+
+impl ThemeName {
+    pub fn palette(&self) -> Palette {
+        match self {
+            ThemeName::Dark => Palette {
+                accent: Color::Cyan,
+                accent_alt: Color::LightCyan,
+                text: Color::White,
+                muted: Color::DarkGray,
+                dim: Color::Gray,
+                warn: Color::Yellow,
+                danger: Color::Red,
+                ok: Color::Green,
+                kind: Color::Magenta,
+                on_accent: Color::Black,
+                highlight: Color::DarkGray,
+            },
+            ThemeName::Light => Palette {
+                accent: Color::Blue,
+                accent_alt: Color::Cyan,
+                text: Color::Black,
+                muted: Color::DarkGray,
+                dim: Color::DarkGray,
+                warn: Color::Rgb(175, 95, 0),
+                danger: Color::Red,
+                ok: Color::Green,
+                kind: Color::Magenta,
+                on_accent: Color::White,
+                highlight: Color::Gray,
+            },
+            ThemeName::Mono => Palette {
+                accent: Color::Reset,
+                accent_alt: Color::Reset,
+                text: Color::Reset,
+                muted: Color::Reset,
+                dim: Color::Reset,
+                warn: Color::Reset,
+                danger: Color::Reset,
+                ok: Color::Reset,
+                kind: Color::Reset,
+                on_accent: Color::Reset,
+                highlight: Color::Reset,
+            },
+        }
+    }
+}
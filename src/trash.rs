@@ -0,0 +1,259 @@
+use anyhow::{bail, Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Move a directory into the trash following the freedesktop.org Trash specification.
+///
+/// Items on the same filesystem as the home trash go to `$XDG_DATA_HOME/Trash`.
+/// Items on other filesystems go to the per-volume `$topdir/.Trash/$uid` (when an
+/// admin-created, sticky `.Trash` exists) or `$topdir/.Trash-$uid`.
+/// Returns the location of the item inside the trash.
+#[cfg(unix)]
+pub fn move_to_trash(path: &Path) -> Result<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+
+    let path = absolute_path(path)?;
+    let device = fs::symlink_metadata(&path)
+        .with_context(|| format!("Cannot read {}", path.display()))?
+        .dev();
+
+    let home_trash = home_trash_dir()?;
+    let trash_dir = if nearest_existing_device(&home_trash) == Some(device) {
+        home_trash
+    } else {
+        volume_trash_dir(&path, device)?
+    };
+
+    trash_into(&path, &trash_dir)
+}
+
+#[cfg(not(unix))]
+pub fn move_to_trash(_path: &Path) -> Result<PathBuf> {
+    bail!("Moving to trash is not supported on this platform")
+}
+
+/// Move `path` into the given trash directory, writing the matching `.trashinfo` record.
+fn trash_into(path: &Path, trash_dir: &Path) -> Result<PathBuf> {
+    let files_dir = trash_dir.join("files");
+    let info_dir = trash_dir.join("info");
+    create_private_dir(&files_dir)?;
+    create_private_dir(&info_dir)?;
+
+    let base_name = path
+        .file_name()
+        .with_context(|| format!("Invalid path {}", path.display()))?
+        .to_string_lossy()
+        .to_string();
+
+    let info_contents = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_trash_path(path),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+    );
+
+    // The .trashinfo file is created first with O_EXCL to reserve the name,
+    // as required by the spec to avoid races with other trash implementations.
+    for counter in 1.. {
+        let name = if counter == 1 {
+            base_name.clone()
+        } else {
+            format!("{}.{}", base_name, counter)
+        };
+
+        let info_path = info_dir.join(format!("{}.trashinfo", name));
+        let target = files_dir.join(&name);
+
+        let mut info_file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Cannot write {}", info_path.display()))
+            }
+        };
+
+        if fs::symlink_metadata(&target).is_ok() {
+            // Stale entry without an info file - leave it alone and pick another name
+            let _ = fs::remove_file(&info_path);
+            continue;
+        }
+
+        if let Err(e) = info_file.write_all(info_contents.as_bytes()) {
+            let _ = fs::remove_file(&info_path);
+            return Err(e).with_context(|| format!("Cannot write {}", info_path.display()));
+        }
+
+        if let Err(e) = fs::rename(path, &target) {
+            let _ = fs::remove_file(&info_path);
+            return Err(e).with_context(|| format!("Cannot move to {}", target.display()));
+        }
+
+        return Ok(target);
+    }
+
+    unreachable!()
+}
+
+fn home_trash_dir() -> Result<PathBuf> {
+    let data_home = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let home = std::env::var_os("HOME").context("HOME is not set")?;
+            PathBuf::from(home).join(".local").join("share")
+        }
+    };
+    Ok(data_home.join("Trash"))
+}
+
+/// Find the trash directory for a path on a filesystem other than the home one.
+#[cfg(unix)]
+fn volume_trash_dir(path: &Path, device: u64) -> Result<PathBuf> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let top_dir = mount_point(path, device);
+    // SAFETY: getuid has no preconditions and cannot fail
+    let uid = unsafe { libc::getuid() };
+
+    // Method 1: an administrator-created $topdir/.Trash with the sticky bit set
+    let shared = top_dir.join(".Trash");
+    if let Ok(meta) = fs::symlink_metadata(&shared) {
+        if meta.is_dir() && meta.mode() & 0o1000 != 0 {
+            let user_dir = shared.join(uid.to_string());
+            if create_private_dir(&user_dir).is_ok() {
+                return Ok(user_dir);
+            }
+        }
+    }
+
+    // Method 2: $topdir/.Trash-$uid
+    let user_dir = top_dir.join(format!(".Trash-{}", uid));
+    create_private_dir(&user_dir).with_context(|| {
+        format!(
+            "No usable trash directory on the filesystem of {}",
+            path.display()
+        )
+    })?;
+
+    let meta = fs::symlink_metadata(&user_dir)?;
+    if !meta.is_dir() || meta.uid() != uid {
+        bail!(
+            "Refusing to use unsafe trash directory {}",
+            user_dir.display()
+        );
+    }
+    if meta.permissions().mode() & 0o077 != 0 {
+        fs::set_permissions(&user_dir, fs::Permissions::from_mode(0o700))?;
+    }
+
+    Ok(user_dir)
+}
+
+/// Walk up from `path` to the topmost ancestor that is still on `device`.
+#[cfg(unix)]
+pub(crate) fn mount_point(path: &Path, device: u64) -> PathBuf {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut top = path.to_path_buf();
+    while let Some(parent) = top.parent() {
+        match fs::metadata(parent) {
+            Ok(meta) if meta.dev() == device => top = parent.to_path_buf(),
+            _ => break,
+        }
+    }
+    top
+}
+
+/// Device of the closest existing ancestor, since the trash may not exist yet.
+#[cfg(unix)]
+fn nearest_existing_device(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    path.ancestors()
+        .find_map(|p| fs::metadata(p).ok())
+        .map(|m| m.dev())
+}
+
+fn create_private_dir(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+    }
+    #[cfg(not(unix))]
+    fs::create_dir_all(dir)?;
+    Ok(())
+}
+
+/// Make `path` absolute without resolving a trailing symlink.
+fn absolute_path(path: &Path) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .with_context(|| format!("Invalid path {}", path.display()))?;
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.canonicalize()?,
+        _ => std::env::current_dir()?,
+    };
+    Ok(parent.join(name))
+}
+
+/// Percent-encode a path the way `.trashinfo` files expect (RFC 2396 URL escaping).
+fn encode_trash_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for &byte in path.to_string_lossy().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_trash_into_writes_info_and_moves() {
+        let temp = tempdir().unwrap();
+        let nm = temp.path().join("my project").join("node_modules");
+        fs::create_dir_all(nm.join("pkg")).unwrap();
+        let trash = temp.path().join("Trash");
+
+        let target = trash_into(&nm, &trash).unwrap();
+
+        assert!(!nm.exists());
+        assert_eq!(target, trash.join("files").join("node_modules"));
+        assert!(target.join("pkg").is_dir());
+
+        let info = fs::read_to_string(trash.join("info").join("node_modules.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\n"));
+        assert!(info.contains("my%20project/node_modules\n"));
+        assert!(info.contains("DeletionDate="));
+    }
+
+    #[test]
+    fn test_trash_into_avoids_name_collisions() {
+        let temp = tempdir().unwrap();
+        let trash = temp.path().join("Trash");
+        let nm1 = temp.path().join("a").join("node_modules");
+        let nm2 = temp.path().join("b").join("node_modules");
+        fs::create_dir_all(&nm1).unwrap();
+        fs::create_dir_all(&nm2).unwrap();
+
+        trash_into(&nm1, &trash).unwrap();
+        let second = trash_into(&nm2, &trash).unwrap();
+
+        assert_eq!(second, trash.join("files").join("node_modules.2"));
+        assert!(trash.join("info").join("node_modules.2.trashinfo").exists());
+    }
+}
@@ -1,13 +1,28 @@
-use crate::scanner::NodeModulesEntry;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crate::cache::CacheStats;
+use crate::filter::PathQuery;
+use crate::history::{History, Report};
+use crate::procs::ProcessTable;
+use crate::project::ProjectInfo;
+use crate::scanner::{self, DeleteMode, NodeModulesEntry};
+use crate::sort::{self, SortKey};
+use crate::theme::Palette;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How long rows changed by watch mode stay highlighted
+const FLASH_TIME: Duration = Duration::from_secs(2);
+
+/// Status bar height limit, so a deletion across many filesystems can't push out the list
+const MAX_STATUS_LINES: usize = 6;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AppMode {
@@ -22,9 +37,15 @@ pub struct App {
     pub scanning: bool,
     pub scan_path: String,
     pub scanning_current_path: String,
+    pub cancel_scan: bool,
     pub total_size: u64,
     pub selected_size: u64,
+    /// What deleting the selection frees, including files hard-linked only between
+    /// selected entries; computed when the confirm popup opens
+    pub confirm_size: u64,
     pub show_help: bool,
+    /// Report shown in the history popup while it is open
+    pub history: Option<Report>,
     pub show_confirm: bool,
     pub message: Option<String>,
     pub should_quit: bool,
@@ -34,6 +55,33 @@ pub struct App {
     pub deleting: bool,
     pub delete_progress: (usize, usize), // (current, total)
     pub delete_current_path: String,
+    pub delete_files_removed: u64,
+    pub delete_bytes_removed: u64,
+    pub delete_bytes_total: u64,
+    pub cancel_delete: bool,
+    pub delete_cancelling: bool,
+    pub delete_mode: DeleteMode,
+    pub dry_run: bool,
+    /// Allow selecting node_modules that are committed to git
+    pub force_tracked: bool,
+    /// Allow selecting node_modules of projects that running processes use
+    pub force: bool,
+    pub sort_key: SortKey,
+    pub sort_reverse: bool,
+    pub visible: Vec<usize>,
+    pub filter: Option<PathQuery>,
+    pub filter_query: String,
+    pub filter_cursor: usize,
+    pub filter_editing: bool,
+    /// What the scan looks for, e.g. `node_modules`
+    pub item_label: String,
+    pub theme: Palette,
+    /// Cache hits of the last finished scan, when the cache is on
+    pub cache_stats: Option<CacheStats>,
+    /// Keep the list live with filesystem events after the scan
+    pub watch: bool,
+    /// When rows were last added or re-sized by the watcher, to flash them
+    pub changed_at: HashMap<PathBuf, Instant>,
 }
 
 impl App {
@@ -45,9 +93,12 @@ impl App {
             scanning: false,
             scan_path: String::new(),
             scanning_current_path: String::new(),
+            cancel_scan: false,
             total_size: 0,
             selected_size: 0,
+            confirm_size: 0,
             show_help: false,
+            history: None,
             show_confirm: false,
             message: None,
             should_quit: false,
@@ -57,20 +108,210 @@ impl App {
             deleting: false,
             delete_progress: (0, 0),
             delete_current_path: String::new(),
+            delete_files_removed: 0,
+            delete_bytes_removed: 0,
+            delete_bytes_total: 0,
+            cancel_delete: false,
+            delete_cancelling: false,
+            delete_mode: DeleteMode::default(),
+            dry_run: false,
+            force_tracked: false,
+            force: false,
+            sort_key: SortKey::default(),
+            sort_reverse: false,
+            visible: Vec::new(),
+            filter: None,
+            filter_query: String::new(),
+            filter_cursor: 0,
+            filter_editing: false,
+            item_label: "node_modules".to_string(),
+            theme: Palette::default(),
+            cache_stats: None,
+            watch: false,
+            changed_at: HashMap::new(),
         }
     }
 
-    pub fn set_entries(&mut self, entries: Vec<NodeModulesEntry>) {
+    pub fn set_entries(&mut self, mut entries: Vec<NodeModulesEntry>) {
+        sort::sort_entries(&mut entries, self.sort_key, self.sort_reverse);
         self.total_size = entries.iter().map(|e| e.size).sum();
         self.entries = entries;
-        if !self.entries.is_empty() {
-            self.state.select(Some(0));
+        self.selected.clear();
+        self.selected_size = 0;
+        self.state.select(None);
+        self.rebuild_view(None);
+    }
+
+    /// Index into `entries` of the row under the cursor
+    pub fn current_index(&self) -> Option<usize> {
+        self.state
+            .selected()
+            .and_then(|i| self.visible.get(i).copied())
+    }
+
+    fn current_path(&self) -> Option<PathBuf> {
+        self.current_index().map(|i| self.entries[i].path.clone())
+    }
+
+    /// Recompute which entries pass the filter and put the cursor back on `cursor_path`
+    fn rebuild_view(&mut self, cursor_path: Option<PathBuf>) {
+        self.visible = match &self.filter {
+            Some(query) => self
+                .entries
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| query.matches(&e.path))
+                .map(|(i, _)| i)
+                .collect(),
+            None => (0..self.entries.len()).collect(),
+        };
+
+        let cursor = cursor_path
+            .and_then(|path| {
+                self.visible
+                    .iter()
+                    .position(|&i| self.entries[i].path == path)
+            })
+            .or_else(|| {
+                self.state
+                    .selected()
+                    .map(|i| i.min(self.visible.len().saturating_sub(1)))
+            })
+            .or(Some(0))
+            .filter(|_| !self.visible.is_empty());
+        self.state.select(cursor);
+    }
+
+    /// Apply the text typed after `/` to the list
+    pub fn update_filter(&mut self) {
+        let cursor_path = self.current_path();
+        self.filter = if self.filter_query.is_empty() {
+            None
+        } else {
+            Some(PathQuery::new(&self.filter_query))
+        };
+        self.rebuild_view(cursor_path);
+    }
+
+    pub fn clear_filter(&mut self) {
+        self.filter_query.clear();
+        self.filter_cursor = 0;
+        self.filter_editing = false;
+        self.update_filter();
+    }
+
+    /// Insert an entry found while the scan is still running at its sorted position
+    pub fn add_entry(&mut self, entry: NodeModulesEntry) {
+        let cursor_path = self.current_path();
+        let (key, reverse) = (self.sort_key, self.sort_reverse);
+        let pos = self
+            .entries
+            .partition_point(|e| sort::compare(e, &entry, key, reverse).is_lt());
+
+        self.total_size += entry.size;
+        self.entries.insert(pos, entry);
+
+        // Keep selection and cursor on the same entries
+        self.selected = self
+            .selected
+            .iter()
+            .map(|&i| if i >= pos { i + 1 } else { i })
+            .collect();
+        self.rebuild_view(cursor_path);
+    }
+
+    /// Re-sort the list by the current key, keeping selection and cursor on the same entries
+    pub fn sort_entries(&mut self) {
+        let cursor_path = self.current_path();
+        let selected_paths = self.selected_paths();
+
+        sort::sort_entries(&mut self.entries, self.sort_key, self.sort_reverse);
+
+        self.restore_selection(&selected_paths);
+        self.rebuild_view(cursor_path);
+    }
+
+    /// Replace the entries at or below `dir` with what the watcher found there.
+    /// New and re-sized entries are flashed; selection and cursor stay on their paths.
+    pub fn apply_watch_update(&mut self, dir: &Path, entries: Vec<NodeModulesEntry>) {
+        let cursor_path = self.current_path();
+        let selected_paths = self.selected_paths();
+
+        let old_sizes: HashMap<PathBuf, u64> = self
+            .entries
+            .iter()
+            .filter(|e| e.path.starts_with(dir))
+            .map(|e| (e.path.clone(), e.size))
+            .collect();
+        self.entries.retain(|e| !e.path.starts_with(dir));
+        let now = Instant::now();
+        for entry in &entries {
+            if old_sizes.get(&entry.path) != Some(&entry.size) {
+                self.changed_at.insert(entry.path.clone(), now);
+            }
         }
+        self.entries.extend(entries);
+        scanner::classify_shared(&mut self.entries);
+
+        sort::sort_entries(&mut self.entries, self.sort_key, self.sort_reverse);
+        self.total_size = self.entries.iter().map(|e| e.size).sum();
+        self.restore_selection(&selected_paths);
+        self.rebuild_view(cursor_path);
+    }
+
+    /// Stop flashing rows that changed more than `FLASH_TIME` ago
+    pub fn expire_flashes(&mut self) {
+        self.changed_at.retain(|_, at| at.elapsed() < FLASH_TIME);
+    }
+
+    fn selected_paths(&self) -> HashSet<PathBuf> {
+        self.selected
+            .iter()
+            .filter_map(|&i| self.entries.get(i).map(|e| e.path.clone()))
+            .collect()
+    }
+
+    /// Select the entries at `paths` again after the list changed order or contents
+    fn restore_selection(&mut self, paths: &HashSet<PathBuf>) {
+        self.selected = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| paths.contains(&e.path))
+            .map(|(i, _)| i)
+            .collect();
+        self.selected_size = self.selected.iter().map(|&i| self.entries[i].size).sum();
+    }
+
+    pub fn cycle_sort(&mut self) {
+        self.sort_key = self.sort_key.next();
+        self.sort_entries();
+    }
+
+    pub fn toggle_sort_reverse(&mut self) {
+        self.sort_reverse = !self.sort_reverse;
+        self.sort_entries();
     }
 
     pub fn toggle_select(&mut self) {
-        if let Some(i) = self.state.selected() {
-            if self.selected.contains(&i) {
+        if let Some(i) = self.current_index() {
+            if self.entries[i].protected {
+                self.message = Some(format!(
+                    "{} is protected and can't be selected",
+                    self.entries[i].path.display()
+                ));
+            } else if let Some(users) = self.entries[i].in_use_summary().filter(|_| !self.force) {
+                self.message = Some(format!(
+                    "{} is in use by {}; stop it or run with --force",
+                    self.entries[i].path.display(),
+                    users
+                ));
+            } else if self.entries[i].is_tracked() && !self.force_tracked {
+                self.message = Some(format!(
+                    "{} is tracked by git; run with --force-tracked to delete it",
+                    self.entries[i].path.display()
+                ));
+            } else if self.selected.contains(&i) {
                 self.selected.remove(&i);
                 self.selected_size -= self.entries[i].size;
             } else {
@@ -80,27 +321,82 @@ impl App {
         }
     }
 
+    /// Select exactly the rows that pass the current filter, except protected
+    /// and (unless forced) git-tracked or in-use ones
     pub fn select_all(&mut self) {
         self.selected.clear();
         self.selected_size = 0;
-        for i in 0..self.entries.len() {
+        for &i in &self.visible {
+            let entry = &self.entries[i];
+            if entry.protected
+                || (entry.is_tracked() && !self.force_tracked)
+                || (!entry.in_use.is_empty() && !self.force)
+            {
+                continue;
+            }
             self.selected.insert(i);
             self.selected_size += self.entries[i].size;
         }
     }
 
+    /// Re-check the selection against running processes just before deleting.
+    /// Entries that came into use are marked and deselected; returns whether any were.
+    pub fn deselect_in_use(&mut self, processes: &ProcessTable) -> bool {
+        if self.force {
+            return false;
+        }
+        let mut busy = Vec::new();
+        for &i in &self.selected {
+            self.entries[i].refresh_in_use(processes);
+            if !self.entries[i].in_use.is_empty() {
+                busy.push(i);
+            }
+        }
+        for i in &busy {
+            self.selected.remove(i);
+            self.selected_size -= self.entries[*i].size;
+        }
+        if busy.is_empty() {
+            return false;
+        }
+        self.message = Some(format!(
+            "{} selected folders are in use by running processes and were deselected",
+            busy.len()
+        ));
+        true
+    }
+
     pub fn deselect_all(&mut self) {
         self.selected.clear();
         self.selected_size = 0;
     }
 
+    /// What deleting the selection together would free, files hard-linked
+    /// between the selected folders included
+    pub fn selected_freeable_size(&self) -> u64 {
+        scanner::combined_size(self.selected.iter().filter_map(|&i| self.entries.get(i)))
+    }
+
+    /// Status line for a dry run of deleting the selection; same size as the confirm popup
+    pub fn dry_run_message(&self) -> String {
+        format!(
+            "Dry run: would {} {} folders, freeing approximately {}",
+            match self.delete_mode {
+                DeleteMode::Permanent => "delete",
+                DeleteMode::Trash => "trash",
+            },
+            self.selected.len(),
+            bytesize::ByteSize::b(self.selected_freeable_size())
+        )
+    }
+
     pub fn next(&mut self) {
-        if self.entries.is_empty() {
+        if self.visible.is_empty() {
             return;
         }
         let i = match self.state.selected() {
             Some(i) => {
-                if i >= self.entries.len() - 1 {
+                if i >= self.visible.len() - 1 {
                     0
                 } else {
                     i + 1
@@ -112,13 +408,13 @@ impl App {
     }
 
     pub fn previous(&mut self) {
-        if self.entries.is_empty() {
+        if self.visible.is_empty() {
             return;
         }
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.entries.len() - 1
+                    self.visible.len() - 1
                 } else {
                     i - 1
                 }
@@ -136,16 +432,25 @@ impl App {
             .collect()
     }
 
-    pub fn remove_deleted(&mut self, deleted_indices: &[usize]) {
-        // Sort indices in reverse order to remove from end first
-        let mut sorted_indices: Vec<usize> = deleted_indices.to_vec();
-        sorted_indices.sort_by(|a, b| b.cmp(a));
-
-        for i in sorted_indices {
-            if i < self.entries.len() {
-                self.entries.remove(i);
+    /// Read the history file and open the history popup
+    pub fn open_history(&mut self) {
+        let events = match History::open().map(|history| history.read()) {
+            Some(Ok(events)) => events,
+            Some(Err(e)) => {
+                self.message = Some(format!("Could not read history: {}", e));
+                return;
             }
-        }
+            None => Vec::new(),
+        };
+        self.history = Some(Report::new(&events));
+    }
+
+    pub fn remove_deleted(&mut self, deleted_paths: &[PathBuf]) {
+        let cursor_path = self.current_path();
+
+        // Match by path - the list may have been re-sorted or grown since deletion started
+        let deleted: HashSet<&PathBuf> = deleted_paths.iter().collect();
+        self.entries.retain(|e| !deleted.contains(&e.path));
 
         // Clear selection and recalculate
         self.selected.clear();
@@ -153,149 +458,282 @@ impl App {
         self.total_size = self.entries.iter().map(|e| e.size).sum();
 
         // Adjust list state
-        if self.entries.is_empty() {
-            self.state.select(None);
-        } else if let Some(current) = self.state.selected() {
-            if current >= self.entries.len() {
-                self.state.select(Some(self.entries.len() - 1));
-            }
-        }
+        self.rebuild_view(cursor_path);
     }
 }
 
 pub fn draw(frame: &mut Frame, app: &mut App) {
+    let palette = app.theme;
+    // Status/Message bar
+    let status_text = if app.filter_editing {
+        format!("/{}", app.filter_query)
+    } else if let Some(ref msg) = app.message {
+        msg.clone()
+    } else if !app.filter_query.is_empty() {
+        format!("Filter: {} (/ to edit, Esc to clear)", app.filter_query)
+    } else if app.scanning {
+        format!(
+            "🔍 Scanning {}: {}",
+            app.scan_path, app.scanning_current_path
+        )
+    } else {
+        String::new()
+    };
+
+    // Multi-line messages (freed space per filesystem) get a taller status bar
+    let status_lines = status_text.lines().count().clamp(1, MAX_STATUS_LINES) as u16;
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3),
             Constraint::Min(10),
-            Constraint::Length(3),
+            Constraint::Length(status_lines + 2),
             Constraint::Length(1),
         ])
         .split(frame.area());
 
     // Header
-    let header = Paragraph::new(vec![Line::from(vec![
+    let mode_color = match app.delete_mode {
+        DeleteMode::Permanent => palette.danger,
+        DeleteMode::Trash => palette.ok,
+    };
+    let mut header_spans = vec![
         Span::styled(
             "nm-remover",
             Style::default()
-                .fg(Color::Cyan)
+                .fg(palette.accent)
                 .add_modifier(Modifier::BOLD),
         ),
-        Span::raw(" - Node Modules Cleaner"),
-    ])])
-    .block(Block::default().borders(Borders::ALL).title("Info"));
+        Span::raw(" - Node Modules Cleaner | Mode: "),
+        Span::styled(app.delete_mode.label(), Style::default().fg(mode_color)),
+    ];
+    if app.dry_run {
+        header_spans.push(Span::styled(
+            " (dry run)",
+            Style::default()
+                .fg(palette.warn)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    let header = Paragraph::new(vec![Line::from(header_spans)])
+        .block(Block::default().borders(Borders::ALL).title("Info"));
     frame.render_widget(header, chunks[0]);
 
     // Main list
     let items: Vec<ListItem> = app
-        .entries
+        .visible
         .iter()
-        .enumerate()
-        .map(|(i, entry)| {
+        .map(|&i| {
+            let entry = &app.entries[i];
             let is_selected = app.selected.contains(&i);
-            let checkbox = if is_selected { "[✓]" } else { "[ ]" };
+            let checkbox = if entry.protected {
+                "🔒 "
+            } else if is_selected {
+                "[✓]"
+            } else {
+                "[ ]"
+            };
 
-            let path_str = entry.path.to_string_lossy();
-            let size_str = entry.size_human();
+            let path_str = entry.display_path();
+            let size_str = entry.size_summary();
             let modified_str = entry.last_modified_human();
+            let activity_str = entry.last_activity_human();
+            let git_span = match &entry.git {
+                Some(git) if git.tracked => {
+                    Span::styled(" [tracked by git]", Style::default().fg(palette.danger))
+                }
+                Some(git) => Span::styled(
+                    format!(
+                        " [commit {}{}]",
+                        entry.last_commit_human(),
+                        if git.dirty { ", dirty" } else { "" }
+                    ),
+                    Style::default().fg(if git.dirty { palette.warn } else { palette.dim }),
+                ),
+                None => Span::raw(""),
+            };
+            let in_use_span = match entry.in_use_summary() {
+                Some(users) => Span::styled(
+                    format!(" [in use: {}]", users),
+                    Style::default().fg(palette.danger),
+                ),
+                None => Span::raw(""),
+            };
+            let (project_str, project_color) = if entry.orphaned {
+                ("orphaned".to_string(), palette.danger)
+            } else {
+                let label = entry
+                    .project
+                    .as_ref()
+                    .map(project_label)
+                    .unwrap_or_default();
+                (label, palette.accent)
+            };
+            let lockfile_str = entry
+                .project
+                .as_ref()
+                .and_then(|p| p.lockfile)
+                .map(|l| l.label())
+                .unwrap_or("");
 
             let content = Line::from(vec![
                 Span::styled(
                     checkbox,
-                    Style::default().fg(if is_selected {
-                        Color::Green
+                    Style::default().fg(if is_selected { palette.ok } else { palette.dim }),
+                ),
+                Span::raw(" "),
+                Span::styled(
+                    format!("{:<8}", entry.kind),
+                    Style::default().fg(palette.kind),
+                ),
+                Span::raw(" "),
+                Span::styled(
+                    format!("{:<PROJECT_WIDTH$}", project_str),
+                    Style::default().fg(project_color),
+                ),
+                Span::raw(" "),
+                Span::styled(
+                    format!("{:<4}", lockfile_str),
+                    Style::default().fg(palette.dim),
+                ),
+                Span::raw(" "),
+                Span::styled(
+                    path_str,
+                    Style::default().fg(if entry.protected {
+                        palette.muted
                     } else {
-                        Color::Gray
+                        palette.text
                     }),
                 ),
                 Span::raw(" "),
-                Span::styled(path_str.to_string(), Style::default().fg(Color::White)),
+                Span::styled(format!("[{}]", size_str), Style::default().fg(palette.warn)),
                 Span::raw(" "),
                 Span::styled(
-                    format!("[{}]", size_str),
-                    Style::default().fg(Color::Yellow),
+                    format!("({})", modified_str),
+                    Style::default().fg(palette.muted),
                 ),
                 Span::raw(" "),
                 Span::styled(
-                    format!("({})", modified_str),
-                    Style::default().fg(Color::DarkGray),
+                    format!("active {}", activity_str),
+                    Style::default().fg(palette.dim),
                 ),
+                git_span,
+                in_use_span,
             ]);
 
-            ListItem::new(content)
+            let item = ListItem::new(content);
+            if app.changed_at.contains_key(&entry.path) {
+                item.style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                item
+            }
         })
         .collect();
 
-    let title = format!(
-        "Found {} node_modules | Total: {} | Selected: {} ({})",
-        app.entries.len(),
+    let found = if app.filter.is_some() {
+        format!("{} of {} shown", app.visible.len(), app.entries.len())
+    } else {
+        format!("Found {} {}", app.entries.len(), app.item_label)
+    };
+    let found = if app.scan_path.is_empty() {
+        found
+    } else {
+        format!("{} in {}", found, app.scan_path)
+    };
+    let mut title = format!(
+        "{} | Total: {} | Selected: {} ({})",
+        found,
         bytesize::ByteSize::b(app.total_size),
         app.selected.len(),
         bytesize::ByteSize::b(app.selected_size)
     );
+    title.push_str(&format!(
+        " | Sort: {} {}",
+        app.sort_key.label(),
+        if app.sort_reverse { "↑" } else { "↓" }
+    ));
+    if app.scanning {
+        title.push_str(" | Still scanning...");
+    } else if let Some(stats) = app.cache_stats {
+        title.push_str(&format!(" | Sizes: {}", stats.describe()));
+    }
+    if app.watch {
+        title.push_str(" | Watching");
+    }
 
     let list = List::new(items)
         .block(Block::default().borders(Borders::ALL).title(title))
         .highlight_style(
             Style::default()
-                .bg(Color::DarkGray)
+                .bg(palette.highlight)
                 .add_modifier(Modifier::BOLD),
         )
         .highlight_symbol("► ");
 
     frame.render_stateful_widget(list, chunks[1], &mut app.state);
 
-    // Status/Message bar
-    let status_text = if let Some(ref msg) = app.message {
-        msg.clone()
-    } else if app.scanning {
-        format!("Scanning: {}", app.scan_path)
-    } else {
-        String::new()
-    };
-
     let status = Paragraph::new(status_text)
-        .style(Style::default().fg(Color::Yellow))
+        .style(Style::default().fg(palette.warn))
         .block(Block::default().borders(Borders::ALL).title("Status"));
     frame.render_widget(status, chunks[2]);
 
+    if app.filter_editing {
+        frame.set_cursor_position((
+            chunks[2].x + app.filter_query[..app.filter_cursor].chars().count() as u16 + 2,
+            chunks[2].y + 1,
+        ));
+    }
+
     // Help bar
-    let help_text =
-        "↑/↓: Navigate | Space: Select | a: All | n: None | d: Delete | ?: Help | q: Quit";
-    let help = Paragraph::new(help_text).style(Style::default().fg(Color::DarkGray));
+    let help_text = if app.scanning {
+        "↑/↓: Navigate | Space: Select | a: All | n: None | d: Delete | /: Filter | s/r: Sort | t: Trash | h: History | ?: Help | Esc: Cancel scan | q: Quit"
+    } else {
+        "↑/↓: Navigate | Space: Select | a: All | n: None | d: Delete | /: Filter | s/r: Sort | t: Trash | h: History | ?: Help | q: Quit"
+    };
+    let help = Paragraph::new(help_text).style(Style::default().fg(palette.muted));
     frame.render_widget(help, chunks[3]);
 
     // Help popup
     if app.show_help {
-        let popup = create_help_popup();
+        let popup = create_help_popup(app.theme);
         let area = centered_rect(60, 60, frame.area());
         frame.render_widget(Clear, area);
         frame.render_widget(popup, area);
     }
 
+    // History popup
+    if let Some(report) = &app.history {
+        let popup = create_history_popup(report, app.theme);
+        let area = centered_rect(80, 80, frame.area());
+        frame.render_widget(Clear, area);
+        frame.render_widget(popup, area);
+    }
+
     // Confirm popup
     if app.show_confirm {
-        let popup = create_confirm_popup(app.selected.len(), app.selected_size);
+        let popup = create_confirm_popup(
+            app.selected.len(),
+            app.confirm_size,
+            app.delete_mode,
+            app.dry_run,
+            app.theme,
+        );
         let area = centered_rect(55, 50, frame.area());
         frame.render_widget(Clear, area);
         frame.render_widget(popup, area);
     }
 
-    // Deleting progress popup
+    // Deleting progress popup - docked at the bottom so the list stays visible
     if app.deleting {
-        let popup = create_deleting_popup(
-            app.delete_progress.0,
-            app.delete_progress.1,
-            &app.delete_current_path,
-        );
-        let area = centered_rect(60, 30, frame.area());
+        let popup = create_deleting_popup(app);
+        let area = bottom_rect(60, 12, chunks[1]);
         frame.render_widget(Clear, area);
         frame.render_widget(popup, area);
     }
 }
 
-fn create_help_popup() -> Paragraph<'static> {
+fn create_help_popup(palette: Palette) -> Paragraph<'static> {
     let text = vec![
         Line::from(""),
         Line::from(vec![Span::styled(
@@ -306,15 +744,22 @@ fn create_help_popup() -> Paragraph<'static> {
         Line::from("  ↑/k      Move cursor up"),
         Line::from("  ↓/j      Move cursor down"),
         Line::from("  Space    Toggle selection"),
-        Line::from("  a        Select all"),
+        Line::from("  a        Select all (except 🔒 protected, git-tracked and in use)"),
         Line::from("  n        Deselect all"),
         Line::from("  d        Delete selected"),
+        Line::from("  t        Toggle trash / permanent delete"),
+        Line::from("  /        Filter by path (substring or glob)"),
+        Line::from("  s        Cycle sort (size, age, path)"),
+        Line::from("  r        Reverse sort order"),
+        Line::from("  w        Toggle watch mode (keep the list live)"),
+        Line::from("  h        Show scan and deletion history"),
         Line::from("  ?        Toggle this help"),
+        Line::from("  Esc      Cancel scan / clear filter"),
         Line::from("  q/Esc    Quit"),
         Line::from(""),
         Line::from(vec![Span::styled(
             "Press any key to close",
-            Style::default().fg(Color::DarkGray),
+            Style::default().fg(palette.muted),
         )]),
     ];
 
@@ -323,32 +768,84 @@ fn create_help_popup() -> Paragraph<'static> {
             Block::default()
                 .title("Help")
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan)),
+                .border_style(Style::default().fg(palette.accent)),
+        )
+        .wrap(Wrap { trim: false })
+}
+
+fn create_history_popup(report: &Report, palette: Palette) -> Paragraph<'static> {
+    let mut text = vec![Line::from("")];
+    for (i, line) in report.lines(12, 10).into_iter().enumerate() {
+        // The totals line and the table headers stand out
+        let header = i == 0 || line.starts_with("Week") || line.ends_with(':');
+        let style = if header {
+            Style::default().add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        text.push(Line::from(Span::styled(format!("  {}", line), style)));
+    }
+    text.push(Line::from(""));
+    text.push(Line::from(vec![Span::styled(
+        "Press any key to close",
+        Style::default().fg(palette.muted),
+    )]));
+
+    Paragraph::new(text)
+        .block(
+            Block::default()
+                .title("History")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(palette.accent)),
         )
         .wrap(Wrap { trim: false })
 }
 
-fn create_confirm_popup(count: usize, size: u64) -> Paragraph<'static> {
+fn create_confirm_popup(
+    count: usize,
+    size: u64,
+    mode: DeleteMode,
+    dry_run: bool,
+    palette: Palette,
+) -> Paragraph<'static> {
     let size_str = bytesize::ByteSize::b(size).to_string();
+    let (question, warning) = match mode {
+        _ if dry_run => (
+            format!("Simulate deleting {} folder(s)?", count),
+            Span::styled(
+                "Dry run: nothing will be removed from disk.",
+                Style::default().fg(palette.warn),
+            ),
+        ),
+        DeleteMode::Permanent => (
+            format!("Are you sure you want to delete {} folder(s)?", count),
+            Span::styled(
+                "This action cannot be undone!",
+                Style::default().fg(palette.danger),
+            ),
+        ),
+        DeleteMode::Trash => (
+            format!("Move {} folder(s) to the trash?", count),
+            Span::styled(
+                "They can be restored from your file manager's trash.",
+                Style::default().fg(palette.ok),
+            ),
+        ),
+    };
     let text = vec![
         Line::from(""),
         Line::from(vec![Span::styled(
             "⚠  WARNING  ⚠",
             Style::default()
-                .fg(Color::Yellow)
+                .fg(palette.warn)
                 .add_modifier(Modifier::BOLD),
         )]),
         Line::from(""),
-        Line::from(format!(
-            "Are you sure you want to delete {} folder(s)?",
-            count
-        )),
+        Line::from(question),
         Line::from(format!("Total size: {}", size_str)),
+        Line::from(format!("Mode: {}", mode.label())),
         Line::from(""),
-        Line::from(vec![Span::styled(
-            "This action cannot be undone!",
-            Style::default().fg(Color::Red),
-        )]),
+        Line::from(vec![warning]),
         Line::from(""),
         Line::from("─────────────────────────────"),
         Line::from(""),
@@ -356,16 +853,16 @@ fn create_confirm_popup(count: usize, size: u64) -> Paragraph<'static> {
             Span::styled(
                 "  [Y]es  ",
                 Style::default()
-                    .fg(Color::Black)
-                    .bg(Color::Green)
+                    .fg(palette.on_accent)
+                    .bg(palette.ok)
                     .add_modifier(Modifier::BOLD),
             ),
             Span::raw("    "),
             Span::styled(
                 "  [N]o  ",
                 Style::default()
-                    .fg(Color::Black)
-                    .bg(Color::Red)
+                    .fg(palette.on_accent)
+                    .bg(palette.danger)
                     .add_modifier(Modifier::BOLD),
             ),
         ]),
@@ -377,21 +874,27 @@ fn create_confirm_popup(count: usize, size: u64) -> Paragraph<'static> {
             Block::default()
                 .title(" Confirm Delete ")
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Red)),
+                .border_style(Style::default().fg(palette.danger)),
         )
         .alignment(ratatui::layout::Alignment::Center)
 }
 
-fn create_deleting_popup(current: usize, total: usize, current_path: &str) -> Paragraph<'static> {
-    let progress_percent = if total > 0 {
-        (current as f64 / total as f64 * 100.0) as u16
+fn create_deleting_popup(app: &App) -> Paragraph<'static> {
+    let palette = app.theme;
+    let (current, total) = app.delete_progress;
+    let current_path = app.delete_current_path.as_str();
+
+    // Track bytes when known, they move smoothly even inside a single big folder
+    let fraction = if app.delete_bytes_total > 0 {
+        (app.delete_bytes_removed as f64 / app.delete_bytes_total as f64).min(1.0)
     } else {
-        0
+        current as f64 / total.max(1) as f64
     };
+    let progress_percent = (fraction * 100.0) as u16;
 
     // Create a simple progress bar
     let bar_width = 30;
-    let filled = (bar_width as f64 * current as f64 / total.max(1) as f64) as usize;
+    let filled = (bar_width as f64 * fraction) as usize;
     let empty = bar_width - filled;
     let progress_bar = format!("[{}{}]", "█".repeat(filled), "░".repeat(empty));
 
@@ -402,27 +905,46 @@ fn create_deleting_popup(current: usize, total: usize, current_path: &str) -> Pa
         current_path.to_string()
     };
 
+    let (heading, hint) = if app.delete_cancelling {
+        (
+            "⏹  Cancelling - finishing folders in progress...",
+            "Remaining folders will be skipped",
+        )
+    } else {
+        (
+            "🗑️  Deleting...",
+            "Esc: Cancel remaining | ↑/↓: Browse list",
+        )
+    };
+
     let text = vec![
-        Line::from(""),
         Line::from(vec![Span::styled(
-            "🗑️  Deleting...",
+            heading,
             Style::default()
-                .fg(Color::Yellow)
+                .fg(palette.warn)
                 .add_modifier(Modifier::BOLD),
         )]),
         Line::from(""),
         Line::from(vec![Span::styled(
             progress_bar,
-            Style::default().fg(Color::Cyan),
+            Style::default().fg(palette.accent),
         )]),
-        Line::from(""),
-        Line::from(format!("{} / {} ({}%)", current, total, progress_percent)),
+        Line::from(format!(
+            "{} / {} folders ({}%)",
+            current, total, progress_percent
+        )),
+        Line::from(format!(
+            "{} files | {} / {}",
+            app.delete_files_removed,
+            bytesize::ByteSize::b(app.delete_bytes_removed),
+            bytesize::ByteSize::b(app.delete_bytes_total)
+        )),
         Line::from(""),
         Line::from(vec![Span::styled(
             display_path,
-            Style::default().fg(Color::DarkGray),
+            Style::default().fg(palette.muted),
         )]),
-        Line::from(""),
+        Line::from(vec![Span::styled(hint, Style::default().fg(palette.muted))]),
     ];
 
     Paragraph::new(text)
@@ -430,12 +952,45 @@ fn create_deleting_popup(current: usize, total: usize, current_path: &str) -> Pa
             Block::default()
                 .title(" Progress ")
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Yellow)),
+                .border_style(Style::default().fg(palette.warn)),
         )
         .alignment(ratatui::layout::Alignment::Center)
         .wrap(Wrap { trim: false })
 }
 
+/// Width of the project column in the list, tags included
+const PROJECT_WIDTH: usize = 32;
+
+/// `name@version` shortened so the tags always fit, e.g. `my-monorepo@1.0.0 [private, ws]`
+fn project_label(project: &ProjectInfo) -> String {
+    let tags = project.tags();
+    let label = project.label().unwrap_or_default();
+    let room = PROJECT_WIDTH.saturating_sub(tags.chars().count());
+    format!("{}{}", truncate(&label, room), tags)
+}
+
+/// Shorten `text` to at most `max` characters, ending with `…` when cut
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        let mut short: String = text.chars().take(max.saturating_sub(1)).collect();
+        short.push('…');
+        short
+    }
+}
+
+/// A horizontally centered rect of fixed height at the bottom of `r`
+fn bottom_rect(percent_x: u16, height: u16, r: Rect) -> Rect {
+    let height = height.min(r.height);
+    let area = Rect {
+        y: r.y + r.height - height,
+        height,
+        ..r
+    };
+    centered_rect(percent_x, 100, area)
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -463,12 +1018,44 @@ pub fn handle_input(app: &mut App) -> std::io::Result<bool> {
                 return Ok(false);
             }
 
+            // While deleting only browsing, cancelling and quitting are allowed
+            if app.deleting {
+                match key.code {
+                    KeyCode::Esc => app.cancel_delete = true,
+                    KeyCode::Down | KeyCode::Char('j') => app.next(),
+                    KeyCode::Up | KeyCode::Char('k') => app.previous(),
+                    KeyCode::Char('q') => app.should_quit = true,
+                    _ => {}
+                }
+                return Ok(false);
+            }
+
             // Handle help popup
             if app.show_help {
                 app.show_help = false;
                 return Ok(false);
             }
 
+            // Handle history popup
+            if app.history.is_some() {
+                app.history = None;
+                return Ok(false);
+            }
+
+            // Handle filter input
+            if app.filter_editing {
+                match key.code {
+                    KeyCode::Enter => app.filter_editing = false,
+                    KeyCode::Esc => app.clear_filter(),
+                    code => {
+                        if edit_input(&mut app.filter_query, &mut app.filter_cursor, code) {
+                            app.update_filter();
+                        }
+                    }
+                }
+                return Ok(false);
+            }
+
             // Handle confirm popup
             if app.show_confirm {
                 match key.code {
@@ -486,6 +1073,17 @@ pub fn handle_input(app: &mut App) -> std::io::Result<bool> {
 
             // Normal mode
             match key.code {
+                KeyCode::Esc if app.scanning => {
+                    app.cancel_scan = true;
+                }
+                KeyCode::Esc if app.filter.is_some() => {
+                    app.clear_filter();
+                }
+                KeyCode::Char('/') => {
+                    app.filter_editing = true;
+                    app.filter_cursor = app.filter_query.len();
+                    app.message = None;
+                }
                 KeyCode::Char('q') | KeyCode::Esc => {
                     app.should_quit = true;
                 }
@@ -504,11 +1102,29 @@ pub fn handle_input(app: &mut App) -> std::io::Result<bool> {
                 KeyCode::Char('n') => {
                     app.deselect_all();
                 }
+                // Kept in its original shape; newer clippy prefers a match guard here
+                #[allow(clippy::collapsible_match)]
                 KeyCode::Char('d') => {
                     if !app.selected.is_empty() {
+                        app.confirm_size = app.selected_freeable_size();
                         app.show_confirm = true;
                     }
                 }
+                KeyCode::Char('t') => {
+                    app.delete_mode = app.delete_mode.toggle();
+                }
+                KeyCode::Char('s') => {
+                    app.cycle_sort();
+                }
+                KeyCode::Char('r') => {
+                    app.toggle_sort_reverse();
+                }
+                KeyCode::Char('w') => {
+                    app.watch = !app.watch;
+                }
+                KeyCode::Char('h') => {
+                    app.open_history();
+                }
                 KeyCode::Char('?') => {
                     app.show_help = true;
                 }
@@ -520,6 +1136,7 @@ pub fn handle_input(app: &mut App) -> std::io::Result<bool> {
 }
 
 pub fn draw_welcome(frame: &mut Frame, app: &mut App) {
+    let palette = app.theme;
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -536,32 +1153,32 @@ pub fn draw_welcome(frame: &mut Frame, app: &mut App) {
         Line::from(""),
         Line::from(vec![Span::styled(
             "  ███╗   ██╗███╗   ███╗      ██████╗ ███████╗███╗   ███╗ ██████╗ ██╗   ██╗███████╗██████╗ ",
-            Style::default().fg(Color::Cyan),
+            Style::default().fg(palette.accent),
         )]),
         Line::from(vec![Span::styled(
             "  ████╗  ██║████╗ ████║      ██╔══██╗██╔════╝████╗ ████║██╔═══██╗██║   ██║██╔════╝██╔══██╗",
-            Style::default().fg(Color::Cyan),
+            Style::default().fg(palette.accent),
         )]),
         Line::from(vec![Span::styled(
             "  ██╔██╗ ██║██╔████╔██║█████╗██████╔╝█████╗  ██╔████╔██║██║   ██║██║   ██║█████╗  ██████╔╝",
-            Style::default().fg(Color::LightCyan),
+            Style::default().fg(palette.accent_alt),
         )]),
         Line::from(vec![Span::styled(
             "  ██║╚██╗██║██║╚██╔╝██║╚════╝██╔══██╗██╔══╝  ██║╚██╔╝██║██║   ██║╚██╗ ██╔╝██╔══╝  ██╔══██╗",
-            Style::default().fg(Color::LightCyan),
+            Style::default().fg(palette.accent_alt),
         )]),
         Line::from(vec![Span::styled(
             "  ██║ ╚████║██║ ╚═╝ ██║      ██║  ██║███████╗██║ ╚═╝ ██║╚██████╔╝ ╚████╔╝ ███████╗██║  ██║",
-            Style::default().fg(Color::White),
+            Style::default().fg(palette.text),
         )]),
         Line::from(vec![Span::styled(
             "  ╚═╝  ╚═══╝╚═╝     ╚═╝      ╚═╝  ╚═╝╚══════╝╚═╝     ╚═╝ ╚═════╝   ╚═══╝  ╚══════╝╚═╝  ╚═╝",
-            Style::default().fg(Color::White),
+            Style::default().fg(palette.text),
         )]),
         Line::from(""),
         Line::from(vec![Span::styled(
             "                           🗑️  Node Modules Cleanup Tool  🗑️",
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            Style::default().fg(palette.warn).add_modifier(Modifier::BOLD),
         )]),
         Line::from(""),
     ];
@@ -572,42 +1189,35 @@ pub fn draw_welcome(frame: &mut Frame, app: &mut App) {
     // Input field
     let input_block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan))
+        .border_style(Style::default().fg(palette.accent))
         .title(Span::styled(
-            " Enter path to scan ",
+            " Enter paths to scan (comma or newline separated) ",
             Style::default()
-                .fg(Color::Yellow)
+                .fg(palette.warn)
                 .add_modifier(Modifier::BOLD),
         ));
 
     let input_area = centered_rect(60, 100, chunks[2]);
 
-    let input_text = if app.scanning {
-        format!("Scanning: {}...", app.scan_path)
-    } else {
-        app.input_path.clone()
-    };
-
-    let input = Paragraph::new(input_text)
-        .style(Style::default().fg(Color::White))
+    // One line per character so the cursor position below still holds
+    let input = Paragraph::new(app.input_path.replace('\n', "↵"))
+        .style(Style::default().fg(palette.text))
         .block(input_block);
 
     frame.render_widget(input, input_area);
 
     // Set cursor position
-    if !app.scanning {
-        frame.set_cursor_position((
-            input_area.x + app.cursor_position as u16 + 1,
-            input_area.y + 1,
-        ));
-    }
+    frame.set_cursor_position((
+        input_area.x + app.input_path[..app.cursor_position].chars().count() as u16 + 1,
+        input_area.y + 1,
+    ));
 
     // Message/error display
     if let Some(ref msg) = app.message {
         let msg_style = if msg.contains("Error") || msg.contains("Invalid") {
-            Style::default().fg(Color::Red)
+            Style::default().fg(palette.danger)
         } else {
-            Style::default().fg(Color::Yellow)
+            Style::default().fg(palette.warn)
         };
         let message = Paragraph::new(msg.as_str())
             .style(msg_style)
@@ -621,81 +1231,33 @@ pub fn draw_welcome(frame: &mut Frame, app: &mut App) {
         Line::from(vec![
             Span::styled(
                 "Enter",
+                Style::default().fg(palette.ok).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" - Start scanning  |  "),
+            Span::styled(
+                "Alt+Enter",
                 Style::default()
-                    .fg(Color::Green)
+                    .fg(palette.accent)
                     .add_modifier(Modifier::BOLD),
             ),
-            Span::raw(" - Start scanning  |  "),
+            Span::raw(" - New line  |  "),
             Span::styled(
                 "Esc/q",
-                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                Style::default()
+                    .fg(palette.danger)
+                    .add_modifier(Modifier::BOLD),
             ),
             Span::raw(" - Quit"),
         ]),
         Line::from(""),
         Line::from(vec![Span::styled(
-            "Tip: Use ~ for home directory (e.g., ~/Projects)",
-            Style::default().fg(Color::DarkGray),
+            "Tip: Use ~ for home directory (e.g., ~/work, ~/oss)",
+            Style::default().fg(palette.muted),
         )]),
     ];
 
     let help = Paragraph::new(help_text).alignment(ratatui::layout::Alignment::Center);
     frame.render_widget(help, chunks[4]);
-
-    // Scanning popup
-    if app.scanning {
-        let popup = create_scanning_popup(&app.scan_path, &app.scanning_current_path);
-        let area = centered_rect(70, 30, frame.area());
-        frame.render_widget(Clear, area);
-        frame.render_widget(popup, area);
-    }
-}
-
-fn create_scanning_popup(root_path: &str, current_path: &str) -> Paragraph<'static> {
-    // Truncate paths if too long
-    let display_root = if root_path.len() > 50 {
-        format!("...{}", &root_path[root_path.len() - 47..])
-    } else {
-        root_path.to_string()
-    };
-
-    let display_current = if current_path.len() > 60 {
-        format!("...{}", &current_path[current_path.len() - 57..])
-    } else if current_path.is_empty() {
-        "Starting...".to_string()
-    } else {
-        current_path.to_string()
-    };
-
-    let text = vec![
-        Line::from(""),
-        Line::from(vec![Span::styled(
-            "🔍 Scanning for node_modules...",
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )]),
-        Line::from(""),
-        Line::from(vec![
-            Span::styled("Root: ", Style::default().fg(Color::Yellow)),
-            Span::styled(display_root, Style::default().fg(Color::White)),
-        ]),
-        Line::from(""),
-        Line::from(vec![Span::styled(
-            display_current,
-            Style::default().fg(Color::DarkGray),
-        )]),
-        Line::from(""),
-    ];
-
-    Paragraph::new(text)
-        .block(
-            Block::default()
-                .title(" Scanning ")
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan)),
-        )
-        .alignment(ratatui::layout::Alignment::Center)
 }
 
 /// Handle input for welcome screen. Returns Some(path) if user submitted a path.
@@ -705,7 +1267,16 @@ pub fn handle_welcome_input(app: &mut App) -> std::io::Result<Option<String>> {
     }
 
     if event::poll(std::time::Duration::from_millis(100))? {
-        if let Event::Key(key) = event::read()? {
+        let event = event::read()?;
+        // A pasted list of paths, one per line, arrives in one piece
+        if let Event::Paste(text) = &event {
+            app.message = None;
+            let text = text.replace("\r\n", "\n");
+            let text = text.trim_end_matches('\n');
+            app.input_path.insert_str(app.cursor_position, text);
+            app.cursor_position += text.len();
+        }
+        if let Event::Key(key) = event {
             if key.kind != KeyEventKind::Press {
                 return Ok(None);
             }
@@ -720,45 +1291,107 @@ pub fn handle_welcome_input(app: &mut App) -> std::io::Result<Option<String>> {
                 KeyCode::Esc => {
                     app.should_quit = true;
                 }
+                KeyCode::Enter if key.modifiers.contains(KeyModifiers::ALT) => {
+                    app.input_path.insert(app.cursor_position, '\n');
+                    app.cursor_position += 1;
+                }
                 KeyCode::Enter => {
                     if !app.input_path.is_empty() {
                         return Ok(Some(app.input_path.clone()));
                     }
                 }
-                KeyCode::Char(c) => {
-                    app.input_path.insert(app.cursor_position, c);
-                    app.cursor_position += 1;
-                }
-                KeyCode::Backspace => {
-                    if app.cursor_position > 0 {
-                        app.cursor_position -= 1;
-                        app.input_path.remove(app.cursor_position);
-                    }
-                }
-                KeyCode::Delete => {
-                    if app.cursor_position < app.input_path.len() {
-                        app.input_path.remove(app.cursor_position);
-                    }
+                code => {
+                    edit_input(&mut app.input_path, &mut app.cursor_position, code);
                 }
-                KeyCode::Left => {
-                    if app.cursor_position > 0 {
-                        app.cursor_position -= 1;
-                    }
-                }
-                KeyCode::Right => {
-                    if app.cursor_position < app.input_path.len() {
-                        app.cursor_position += 1;
-                    }
-                }
-                KeyCode::Home => {
-                    app.cursor_position = 0;
-                }
-                KeyCode::End => {
-                    app.cursor_position = app.input_path.len();
-                }
-                _ => {}
             }
         }
     }
     Ok(None)
 }
+
+/// Apply a cursor-editing key to a single-line text input.
+/// `cursor` is a byte offset into `input`. Returns true if the text changed.
+fn edit_input(input: &mut String, cursor: &mut usize, code: KeyCode) -> bool {
+    match code {
+        KeyCode::Char(c) => {
+            input.insert(*cursor, c);
+            *cursor += c.len_utf8();
+            true
+        }
+        KeyCode::Backspace if *cursor > 0 => {
+            let prev = input[..*cursor]
+                .chars()
+                .next_back()
+                .map_or(0, |c| c.len_utf8());
+            *cursor -= prev;
+            input.remove(*cursor);
+            true
+        }
+        KeyCode::Delete if *cursor < input.len() => {
+            input.remove(*cursor);
+            true
+        }
+        KeyCode::Left if *cursor > 0 => {
+            *cursor -= input[..*cursor]
+                .chars()
+                .next_back()
+                .map_or(0, |c| c.len_utf8());
+            false
+        }
+        KeyCode::Right if *cursor < input.len() => {
+            *cursor += input[*cursor..].chars().next().map_or(0, |c| c.len_utf8());
+            false
+        }
+        KeyCode::Home => {
+            *cursor = 0;
+            false
+        }
+        KeyCode::End => {
+            *cursor = input.len();
+            false
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::SharedLink;
+    use std::sync::Arc;
+
+    fn entry(path: &str, size: u64, links: Vec<SharedLink>) -> NodeModulesEntry {
+        NodeModulesEntry {
+            path: PathBuf::from(path),
+            size,
+            shared_links: Arc::new(links),
+            ..NodeModulesEntry::default()
+        }
+    }
+
+    #[test]
+    fn test_dry_run_counts_links_between_selected_folders() {
+        let link = SharedLink {
+            id: (1, 1),
+            links: 1,
+            nlink: 2,
+            allocated: 4000,
+        };
+        let mut app = App::new();
+        app.set_entries(vec![
+            entry("/a/node_modules", 1000, vec![link]),
+            entry("/b/node_modules", 2000, vec![link]),
+        ]);
+        app.select_all();
+
+        assert_eq!(app.selected_size, 3000);
+        assert_eq!(app.selected_freeable_size(), 7000);
+        assert_eq!(
+            app.dry_run_message(),
+            format!(
+                "Dry run: would delete 2 folders, freeing approximately {}",
+                bytesize::ByteSize::b(7000)
+            )
+        );
+    }
+}
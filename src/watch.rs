@@ -0,0 +1,345 @@
+use anyhow::Result;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::scanner::{
+    find_artifacts, scan_subtree, CancelToken, EntryCallback, NodeModulesEntry, ProgressCallback,
+    ScanOptions,
+};
+
+/// How long the filesystem has to be quiet before changed directories are re-scanned;
+/// a single `npm install` produces thousands of events
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Changes are picked up at least this often while an install is still running
+const MAX_DELAY: Duration = Duration::from_secs(3);
+
+/// Everything found at or below `dir` after it changed.
+/// Replaces whatever the list had at or below `dir`; an empty list means it is gone.
+#[derive(Debug)]
+pub struct WatchUpdate {
+    pub dir: PathBuf,
+    pub entries: Vec<NodeModulesEntry>,
+}
+
+/// Keeps scan results current while other people install and remove dependencies.
+///
+/// Starting walks the roots again without measuring anything and puts a non-recursive
+/// watch on every directory it enters and on every artifact; only artifacts that
+/// differ from the `known` ones the caller already lists are scanned. That is
+/// one watch per directory outside the artifacts, not per file inside them, but large
+/// trees can still run into the inotify limit; see `take_error`.
+pub struct LiveWatcher {
+    updates: mpsc::Receiver<WatchUpdate>,
+    stop: CancelToken,
+    handle: Option<thread::JoinHandle<()>>,
+    /// The first directory that couldn't be watched and why
+    first_error: Arc<OnceLock<String>>,
+    error_reported: AtomicBool,
+}
+
+impl LiveWatcher {
+    pub fn start(
+        roots: Vec<PathBuf>,
+        options: ScanOptions,
+        known: HashSet<PathBuf>,
+    ) -> Result<LiveWatcher> {
+        let (event_tx, event_rx) = mpsc::channel();
+        let watcher = Arc::new(Mutex::new(notify::recommended_watcher(event_tx)?));
+        let (update_tx, updates) = mpsc::channel();
+        let stop = CancelToken::new();
+
+        let first_error = Arc::new(OnceLock::new());
+        let options = watching_options(options, &watcher, stop.clone(), &first_error);
+        let mut state = WatchState {
+            roots,
+            options,
+            artifacts: HashSet::new(),
+            updates: update_tx,
+        };
+        let thread_stop = stop.clone();
+
+        let handle = thread::spawn(move || {
+            // The watcher has to live as long as the thread; dropping it stops the events
+            let _watcher = watcher;
+            state.seed(known);
+
+            let mut pending: HashSet<PathBuf> = HashSet::new();
+            let mut first_change = Instant::now();
+            let mut last_change = Instant::now();
+            while !thread_stop.is_cancelled() {
+                match event_rx.recv_timeout(Duration::from_millis(100)) {
+                    Ok(Ok(event)) if event.need_rescan() => state.rescan_roots(),
+                    Ok(Ok(event)) if is_change(&event) => {
+                        if pending.is_empty() {
+                            first_change = Instant::now();
+                        }
+                        last_change = Instant::now();
+                        pending.extend(event.paths);
+                    }
+                    Ok(_) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+
+                let settled = last_change.elapsed() >= SETTLE_TIME;
+                if !pending.is_empty() && (settled || first_change.elapsed() >= MAX_DELAY) {
+                    state.process(std::mem::take(&mut pending));
+                }
+            }
+        });
+
+        Ok(LiveWatcher {
+            updates,
+            stop,
+            handle: Some(handle),
+            first_error,
+            error_reported: AtomicBool::new(false),
+        })
+    }
+
+    /// The first watch that failed, once; everything after it may be missed
+    pub fn take_error(&self) -> Option<String> {
+        let error = self.first_error.get()?;
+        (!self.error_reported.swap(true, Ordering::Relaxed)).then(|| error.clone())
+    }
+
+    /// Updates that arrived since the last call
+    pub fn try_updates(&self) -> Vec<WatchUpdate> {
+        self.updates.try_iter().collect()
+    }
+
+    /// Stop watching; a re-scan in progress is cancelled
+    pub fn stop(mut self) {
+        self.stop.cancel();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Scan options that watch every directory the scan walks and every artifact it reports
+fn watching_options(
+    options: ScanOptions,
+    watcher: &Arc<Mutex<RecommendedWatcher>>,
+    cancel: CancelToken,
+    first_error: &Arc<OnceLock<String>>,
+) -> ScanOptions {
+    let dir_watcher = Arc::clone(watcher);
+    let dir_error = Arc::clone(first_error);
+    let progress: ProgressCallback = Arc::new(Mutex::new(move |dir: &str| {
+        watch_dir(&dir_watcher, Path::new(dir), &dir_error);
+    }));
+
+    let artifact_watcher = Arc::clone(watcher);
+    let artifact_error = Arc::clone(first_error);
+    let on_entry: EntryCallback = Arc::new(Mutex::new(move |entry: &NodeModulesEntry| {
+        // A symlinked artifact is only a link; its target isn't ours to watch
+        if entry.link_target.is_none() {
+            watch_dir(&artifact_watcher, &entry.path, &artifact_error);
+        }
+    }));
+
+    ScanOptions {
+        progress_callback: Some(progress),
+        entry_callback: Some(on_entry),
+        cancel,
+        ..options
+    }
+}
+
+/// Watch `dir` and remember the first failure. Directories removed before the
+/// watch could be added are not an error.
+fn watch_dir(watcher: &Mutex<RecommendedWatcher>, dir: &Path, first_error: &OnceLock<String>) {
+    let Ok(mut w) = watcher.lock() else {
+        return;
+    };
+    let error = match w.watch(dir, RecursiveMode::NonRecursive) {
+        Ok(()) => return,
+        Err(e) => e,
+    };
+    let message = match &error.kind {
+        notify::ErrorKind::PathNotFound => return,
+        notify::ErrorKind::Io(io) if io.kind() == std::io::ErrorKind::NotFound => return,
+        notify::ErrorKind::MaxFilesWatch => format!(
+            "watch limit reached at {} (raise fs.inotify.max_user_watches)",
+            dir.display()
+        ),
+        _ => format!("can't watch {}: {}", dir.display(), error),
+    };
+    let _ = first_error.set(message);
+}
+
+/// Directory entries appearing, disappearing or being renamed; reads and file
+/// content changes don't affect the list
+fn is_change(event: &Event) -> bool {
+    matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(_)
+    ) && !matches!(
+        event.kind,
+        EventKind::Modify(notify::event::ModifyKind::Data(_))
+    )
+}
+
+struct WatchState {
+    roots: Vec<PathBuf>,
+    options: ScanOptions,
+    /// Paths of all artifacts currently reported
+    artifacts: HashSet<PathBuf>,
+    updates: mpsc::Sender<WatchUpdate>,
+}
+
+impl WatchState {
+    /// Watch the roots and report where they differ from the `known` artifacts
+    fn seed(&mut self, known: HashSet<PathBuf>) {
+        for root in self.roots.clone() {
+            if let Ok(found) = find_artifacts(&root, &self.options) {
+                self.artifacts.extend(found);
+            }
+        }
+        if self.options.cancel.is_cancelled() {
+            return;
+        }
+
+        let mut added: Vec<PathBuf> = self.artifacts.difference(&known).cloned().collect();
+        added.sort();
+        for dir in added {
+            self.rescan(&dir);
+        }
+        for dir in known.difference(&self.artifacts) {
+            let _ = self.updates.send(WatchUpdate {
+                dir: dir.clone(),
+                entries: Vec::new(),
+            });
+        }
+    }
+
+    fn rescan_roots(&mut self) {
+        for root in self.roots.clone() {
+            self.rescan(&root);
+        }
+    }
+
+    /// Re-scan the directories touched by `paths`
+    fn process(&mut self, paths: HashSet<PathBuf>) {
+        let mut targets: Vec<PathBuf> = paths
+            .into_iter()
+            .filter_map(|path| self.target_for(path))
+            .collect();
+        targets.sort();
+        targets.dedup();
+
+        // Sorted, so a directory comes before everything inside it
+        let mut covered: Vec<PathBuf> = Vec::new();
+        for target in targets {
+            if !covered.iter().any(|dir| target.starts_with(dir)) {
+                self.rescan(&target);
+                covered.push(target);
+            }
+        }
+    }
+
+    /// The directory to re-scan for a changed path: the artifact it belongs to,
+    /// or the path itself when it is (or was) a directory outside of any artifact
+    fn target_for(&self, path: PathBuf) -> Option<PathBuf> {
+        if let Some(artifact) = self.artifacts.iter().find(|a| path.starts_with(a)) {
+            return Some(artifact.clone());
+        }
+        // Files next to projects don't change what is found
+        if path.exists() && !path.is_dir() {
+            return None;
+        }
+        Some(path)
+    }
+
+    fn rescan(&mut self, dir: &Path) {
+        let Some(root) = self.roots.iter().find(|root| dir.starts_with(root)) else {
+            return;
+        };
+        let Ok(entries) = scan_subtree(root, dir, &self.options) else {
+            return;
+        };
+        if self.options.cancel.is_cancelled() {
+            return;
+        }
+
+        let before = self.artifacts.len();
+        self.artifacts.retain(|a| !a.starts_with(dir));
+        let removed = before != self.artifacts.len();
+        if entries.is_empty() && !removed {
+            return;
+        }
+        self.artifacts
+            .extend(entries.iter().map(|entry| entry.path.clone()));
+
+        let _ = self.updates.send(WatchUpdate {
+            dir: dir.to_path_buf(),
+            entries,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Wait until one of the updates received so far (kept in `received`) matches
+    fn wait_for(
+        watcher: &LiveWatcher,
+        received: &mut Vec<WatchUpdate>,
+        found: impl Fn(&WatchUpdate) -> bool,
+    ) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            received.extend(watcher.try_updates());
+            if received.iter().any(&found) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    #[test]
+    fn test_reports_new_and_removed_node_modules() {
+        let temp = tempdir().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        let existing = root.join("old").join("node_modules");
+        fs::create_dir_all(&existing).unwrap();
+
+        let gone = root.join("gone").join("node_modules");
+        let known = HashSet::from([existing.clone(), gone.clone()]);
+        let unknown = root.join("unlisted").join("node_modules");
+        fs::create_dir_all(&unknown).unwrap();
+
+        // Only what differs from the known list is reported at the start
+        let watcher =
+            LiveWatcher::start(vec![root.clone()], ScanOptions::default(), known).unwrap();
+        let mut received = Vec::new();
+        assert!(wait_for(&watcher, &mut received, |u| u.dir == unknown
+            && u.entries.len() == 1));
+        assert!(wait_for(&watcher, &mut received, |u| u.dir == gone
+            && u.entries.is_empty()));
+        assert!(!received.iter().any(|u| u.dir == existing || u.dir == root));
+
+        let added = root.join("new").join("node_modules");
+        fs::create_dir_all(&added).unwrap();
+        assert!(wait_for(&watcher, &mut received, |u| u
+            .entries
+            .iter()
+            .any(|e| e.path == added)));
+
+        fs::remove_dir_all(&existing).unwrap();
+        assert!(wait_for(&watcher, &mut received, |u| u.dir == existing
+            && u.entries.is_empty()));
+
+        watcher.stop();
+    }
+}